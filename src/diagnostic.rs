@@ -0,0 +1,318 @@
+use std::fmt;
+use std::io::{stderr, IsTerminal, Write};
+
+use crate::eval::RuntimeError;
+use crate::span::Span;
+use crate::{Token, TokenType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Which stage of running a script noticed the problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Lex,
+    Parse,
+    Resolve,
+    Runtime,
+}
+
+/// One level of a runtime stack trace: the function that was running, or
+/// `None` for the top-level script, and the line it had reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub function: Option<String>,
+    pub line: u32,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.function {
+            Some(name) => write!(f, "[line {}] in {}()", self.line, name),
+            None => write!(f, "[line {}] in script", self.line),
+        }
+    }
+}
+
+/// A problem found in a script, from any phase. Everything that reports an
+/// error to the user goes through this, so they all print the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub phase: Phase,
+    pub span: Span,
+    pub message: String,
+    /// Extra context shown under the message, in the pretty format only.
+    pub notes: Vec<String>,
+    /// Where a runtime error raised inside a function was called from,
+    /// innermost frame first. Empty when it never left the top level.
+    pub trace: Vec<Frame>,
+    /// Exit status if this ends the run: 65 before running, 70 at runtime, 0 for a warning.
+    pub exit: i32,
+}
+
+impl Diagnostic {
+    pub fn error(phase: Phase, span: Span, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            phase,
+            span,
+            message,
+            notes: Vec::new(),
+            trace: Vec::new(),
+            exit: if phase == Phase::Runtime { 70 } else { 65 },
+        }
+    }
+
+    pub fn warning(phase: Phase, span: Span, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            exit: 0,
+            ..Self::error(phase, span, message)
+        }
+    }
+
+    /// Compile error reported at `token`, worded the way Lox does:
+    /// `Error at 'tok': msg`, or `Error at end: msg` once input has run out.
+    pub fn at_token(phase: Phase, token: &Token, msg: &str) -> Self {
+        if token._type == TokenType::EOF {
+            return Self::error(phase, token._span, format!("Error at end: {msg}"));
+        }
+        Self::at(phase, token._string, token._span, msg)
+    }
+
+    /// Same as `at_token`, for errors found after parsing where only the
+    /// lexeme and span of the offending name are left.
+    pub fn at(phase: Phase, lexeme: &str, span: Span, msg: &str) -> Self {
+        Self::error(phase, span, format!("Error at '{lexeme}': {msg}"))
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Long form for people reading errors in a terminal: the file, line and
+    /// column, the offending source line, and a `^^^` under the bad span.
+    pub fn render_pretty(&self, file: &str, source: &str, color: bool) -> String {
+        let paint = |code: &str, text: &str| {
+            if color {
+                format!("\x1b[{code}m{text}\x1b[0m")
+            } else {
+                text.to_string()
+            }
+        };
+        let (label, accent) = match self.severity {
+            Severity::Error => ("error", "1;31"),
+            Severity::Warning => ("warning", "1;33"),
+        };
+        let label = paint(accent, label);
+        // the plain format's "Error" lead-in is redundant after the label, so
+        // "Error: msg" becomes "msg" and "Error at 'x': msg" "at 'x': msg"
+        let message = self
+            .message
+            .strip_prefix("Error: ")
+            .or_else(|| self.message.strip_prefix("Error "))
+            .unwrap_or(&self.message);
+
+        let line = self.span.line.max(1);
+        let text = source.lines().nth(line as usize - 1).unwrap_or("");
+        let gutter = " ".repeat(line.to_string().len());
+        let bar = paint("1;34", "|");
+
+        // keep tabs in the padding so the carets land under the right column
+        let before: String = text
+            .chars()
+            .take(self.span.column.saturating_sub(1) as usize)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let rest = text.chars().count().saturating_sub(before.chars().count());
+        let width = source
+            .get(self.span.start..self.span.end)
+            .map_or(0, |lexeme| {
+                lexeme.chars().take_while(|&c| c != '\n').count()
+            })
+            .min(rest)
+            .max(1);
+
+        let arrow = paint("1;34", "-->");
+        let number = paint("1;34", &line.to_string());
+        let carets = paint(accent, &"^".repeat(width));
+        let column = self.span.column.max(1);
+        let mut out = format!("{label}: {}\n", paint("1", message));
+        out.push_str(&format!("{gutter}{arrow} {file}:{line}:{column}\n"));
+        out.push_str(&format!("{gutter} {bar}\n"));
+        out.push_str(&format!("{number} {bar} {text}\n"));
+        out.push_str(&format!("{gutter} {bar} {before}{carets}"));
+        for frame in &self.trace {
+            out.push_str(&format!("\n{gutter} {} {frame}", paint("1;34", "=")));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("\n{gutter} {} note: {note}", paint("1;34", "=")));
+        }
+        out
+    }
+}
+
+/// How the CLI prints diagnostics. `Plain` is the one-line format the Lox
+/// test suite checks for; `Pretty` is opt-in with `--diagnostics=pretty`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    Pretty,
+}
+
+/// Prints diagnostics for one source file in the chosen style.
+pub struct Reporter<'a> {
+    pub style: Style,
+    pub file: &'a str,
+    pub source: &'a str,
+}
+
+impl Reporter<'_> {
+    pub fn report(&self, diagnostics: &[Diagnostic]) {
+        let mut stderr = stderr();
+        let color = stderr.is_terminal();
+        for diagnostic in diagnostics {
+            let rendered = match self.style {
+                Style::Plain if diagnostic.severity == Severity::Warning => continue,
+                Style::Plain => diagnostic.to_string(),
+                Style::Pretty => diagnostic.render_pretty(self.file, self.source, color),
+            };
+            writeln!(stderr, "{rendered}").unwrap();
+        }
+    }
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(error: RuntimeError) -> Self {
+        let mut diagnostic = Self::error(Phase::Runtime, error.span, error.kind.to_string());
+        if !error.trace.is_empty() {
+            // each call ran in the frame of the one that made it, and the
+            // outermost call was made from the script itself
+            let mut line = error.span.line;
+            for call in error.trace {
+                diagnostic.trace.push(Frame {
+                    function: Some(call.function),
+                    line,
+                });
+                line = call.call_line;
+            }
+            diagnostic.trace.push(Frame {
+                function: None,
+                line,
+            });
+        }
+        diagnostic
+    }
+}
+
+// compile errors lead with the line and runtime errors trail it, which is
+// what the Lox test suite expects on stderr
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.phase {
+            Phase::Runtime if !self.trace.is_empty() => {
+                write!(f, "{}", self.message)?;
+                for frame in &self.trace {
+                    write!(f, "\n{frame}")?;
+                }
+            }
+            Phase::Runtime => write!(f, "{}\n[line {}]", self.message, self.span.line)?,
+            _ => write!(f, "[line {}] {}", self.span.line, self.message)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::Evaluator;
+    use crate::lex::Lexer;
+    use crate::parse::Parser;
+    use crate::resolve::Resolver;
+
+    // runs a script that should fail, and renders its error the way `run` does
+    fn runtime_error(source: &str) -> String {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.by_ref().collect();
+        let statments = Parser::new(tokens).stmt_parser().unwrap();
+        let mut evaluator = Evaluator::new();
+        evaluator.locals = Resolver::new().resolve(&statments).unwrap();
+        for statment in &statments {
+            if let Err(error) = evaluator.execute(statment) {
+                return Diagnostic::from(error).to_string();
+            }
+        }
+        panic!("{source} ran without an error");
+    }
+
+    #[test]
+    fn pretty_messages_drop_the_plain_error_lead_in() {
+        let source = "print ;";
+        let span = Span::new(1, 7, 6, 7);
+        let cases = [
+            (
+                "Error: Unexpected character: @",
+                "error: Unexpected character: @",
+            ),
+            (
+                "Error at ';': Expect expression.",
+                "error: at ';': Expect expression.",
+            ),
+            ("Error at end: Expect ';'.", "error: at end: Expect ';'."),
+        ];
+        for (message, expected) in cases {
+            let diagnostic = Diagnostic::error(Phase::Parse, span, message.to_string());
+            let rendered = diagnostic.render_pretty("test.lox", source, false);
+            assert_eq!(rendered.lines().next(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn runtime_traces_list_each_call_from_where_it_was_made() {
+        let cases = [
+            (
+                "print nil + 1;",
+                "Operands must be two numbers or two strings.\n[line 1]",
+            ),
+            (
+                "fun f() {\n  return nil + 1;\n}\nf();",
+                "Operands must be two numbers or two strings.\n\
+                 [line 2] in f()\n\
+                 [line 4] in script",
+            ),
+            (
+                "fun f() {\n  return -nil;\n}\nfun g() {\n  f();\n}\n\ng();",
+                "Operand must be a number.\n\
+                 [line 2] in f()\n\
+                 [line 5] in g()\n\
+                 [line 8] in script",
+            ),
+            (
+                "class A {\n  init() {\n    this.m();\n  }\n  m() { missing; }\n}\nA();",
+                "Undefined variable 'missing'.\n\
+                 [line 5] in m()\n\
+                 [line 3] in init()\n\
+                 [line 7] in script",
+            ),
+            (
+                "fun f(a) { return -a; }\nf(\n  nil\n);",
+                "Operand must be a number.\n\
+                 [line 1] in f()\n\
+                 [line 4] in script",
+            ),
+            (
+                "fun f() {}\nf();\nprint -nil;",
+                "Operand must be a number.\n[line 3]",
+            ),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(runtime_error(source), expected, "{source}");
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::eval::Value;
+
+/// Scopes are shared: a block, a running call and any closure created inside
+/// them can all hold the same one, and it lives as long as the last of them.
+pub type EnvRef<'src> = Rc<RefCell<Environment<'src>>>;
+
+/// Where the resolver found a local variable: `depth` scopes out from the
+/// one in use, at index `slot` in that scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub depth: usize,
+    pub slot: usize,
+}
+
+/// One lexical scope. Locals live in `slots`, at the index the resolver gave
+/// them, so reading one never hashes its name. Only globals are kept by name,
+/// because they can be used before they're declared and the resolver leaves
+/// them alone. Name lookups that miss here fall through to the enclosing
+/// scope, so the innermost declaration of a name always wins.
+#[derive(Default)]
+pub struct Environment<'src> {
+    values: HashMap<String, Value<'src>>,
+    // `None` for a slot whose declaration hasn't run, such as the `var` in
+    // an `if` branch that wasn't taken
+    slots: Vec<Option<Value<'src>>>,
+    enclosing: Option<EnvRef<'src>>,
+}
+
+impl<'src> Environment<'src> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_enclosing(enclosing: EnvRef<'src>) -> Self {
+        Self {
+            enclosing: Some(enclosing),
+            ..Self::default()
+        }
+    }
+
+    pub fn into_ref(self) -> EnvRef<'src> {
+        Rc::new(RefCell::new(self))
+    }
+
+    /// The scope `distance` hops out from `environment`.
+    pub fn ancestor(environment: &EnvRef<'src>, distance: usize) -> EnvRef<'src> {
+        let mut environment = environment.clone();
+        for _ in 0..distance {
+            let enclosing = environment.borrow().enclosing.clone();
+            environment = enclosing.expect("resolved depth is deeper than the scope chain");
+        }
+        environment
+    }
+
+    /// Reads the local the resolver bound a name to, without probing the
+    /// scopes in between or looking at the name at all.
+    pub fn get_at(environment: &EnvRef<'src>, binding: Binding) -> Option<Value<'src>> {
+        let scope = Self::ancestor(environment, binding.depth);
+        let value = scope.borrow().slots.get(binding.slot).cloned().flatten();
+        value
+    }
+
+    pub fn assign_at(environment: &EnvRef<'src>, binding: Binding, value: Value<'src>) -> bool {
+        let scope = Self::ancestor(environment, binding.depth);
+        let mut scope = scope.borrow_mut();
+        match scope.slots.get_mut(binding.slot) {
+            Some(slot @ Some(_)) => {
+                *slot = Some(value);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Declares a global.
+    pub fn define(&mut self, name: String, value: Value<'src>) {
+        self.values.insert(name, value);
+    }
+
+    /// Declares the local the resolver put in `slot` of this scope. Running
+    /// the same declaration again, as a loop body can, overwrites it.
+    pub fn define_at(&mut self, slot: usize, value: Value<'src>) {
+        if self.slots.len() <= slot {
+            self.slots.resize(slot + 1, None);
+        }
+        self.slots[slot] = Some(value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value<'src>> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.enclosing.as_ref()?.borrow().get(name),
+        }
+    }
+
+    /// Overwrites the nearest existing binding of `name`. Returns false if no
+    /// scope in the chain declares it.
+    pub fn assign(&mut self, name: &str, value: Value<'src>) -> bool {
+        if let Some(slot) = self.values.get_mut(name) {
+            *slot = value;
+            return true;
+        }
+        match self.enclosing.as_ref() {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => false,
+        }
+    }
+}
@@ -1,410 +1,1080 @@
 use core::fmt;
+use std::cell::RefCell;
 use std::clone;
 use std::collections::HashMap;
 use std::ops::Deref;
+use std::rc::Rc;
 
+use crate::environment::{Binding, EnvRef, Environment};
 use crate::expr::Expr;
-use crate::expr::ExprError;
+use crate::expr::ExprKind;
 use crate::expr::ExprLiteral;
+use crate::interpret::Flow;
+use crate::smnt::FunctionDecl;
+use crate::span::Span;
 use crate::Token;
+use crate::TokenType;
+
+/// How many calls may be running at once before a call fails with a stack
+/// overflow, rather than overflowing the interpreter's own stack.
+const MAX_CALL_DEPTH: usize = 256;
 
 #[derive(Clone)]
 
-pub struct Evaluator {
-    pub line: u32,
-    pub symbols: HashMap<String, Value>,
+pub struct Evaluator<'src> {
+    pub environment: EnvRef<'src>,
+    pub globals: EnvRef<'src>,
+    /// Scope and slot of every local variable reference and declaration,
+    /// keyed by the byte offset of its name token. Anything missing is a
+    /// global.
+    pub locals: HashMap<usize, Binding>,
+    /// Calls currently running.
+    calls: usize,
 }
 
 #[derive(Debug, Clone)]
-pub enum Value {
+pub enum Value<'src> {
     String(String),
-    Number(f32),
+    Number(f64),
     Nil,
     Bool(bool),
+    Function(Rc<Function<'src>>),
+    Class(Rc<Class<'src>>),
+    Instance(Rc<RefCell<Instance<'src>>>),
+}
+
+/// A function value: its declaration plus the scope it was declared in,
+/// which the body keeps resolving names against after that scope has exited.
+pub struct Function<'src> {
+    pub declaration: Rc<FunctionDecl<'src>>,
+    pub closure: EnvRef<'src>,
+    pub is_initializer: bool,
+}
+
+impl<'src> Function<'src> {
+    /// Copy of this method whose closure has `this` bound to `instance`.
+    pub fn bind(&self, instance: Value<'src>) -> Function<'src> {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        environment.define_at(0, instance);
+        Function {
+            declaration: self.declaration.clone(),
+            closure: environment.into_ref(),
+            is_initializer: self.is_initializer,
+        }
+    }
+}
+
+// the closure can (and for recursive functions does) refer back to this
+// function, so don't try to print it
+impl fmt::Debug for Function<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Function({})", self.declaration.name._string)
+    }
+}
+
+#[derive(Debug)]
+pub struct Class<'src> {
+    pub name: String,
+    pub superclass: Option<Rc<Class<'src>>>,
+    pub methods: HashMap<String, Rc<Function<'src>>>,
+}
+
+impl<'src> Class<'src> {
+    /// Looks the method up on this class first, then up the superclass chain.
+    pub fn find_method(&self, name: &str) -> Option<Rc<Function<'src>>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+
+    // calling a class takes whatever its initializer takes
+    pub fn arity(&self) -> usize {
+        self.find_method("init")
+            .map_or(0, |init| init.declaration.params.len())
+    }
+}
+
+pub struct Instance<'src> {
+    pub class: Rc<Class<'src>>,
+    pub fields: HashMap<String, Value<'src>>,
+}
+
+impl<'src> Instance<'src> {
+    pub fn new(class: Rc<Class<'src>>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Fields shadow methods; a method found on the class comes back bound to
+    /// this instance so `this` works once it's called.
+    pub fn get(
+        instance: &Rc<RefCell<Instance<'src>>>,
+        name: &Token<'src>,
+    ) -> Result<Value<'src>, RuntimeError> {
+        let this = instance.borrow();
+        if let Some(value) = this.fields.get(name._string) {
+            return Ok(value.clone());
+        }
+        match this.class.find_method(name._string) {
+            Some(method) => Ok(Value::Function(Rc::new(
+                method.bind(Value::Instance(instance.clone())),
+            ))),
+            None => Err(RuntimeError::new(
+                RuntimeErrorKind::UndefinedProperty(name._string.to_string()),
+                name._span,
+            )),
+        }
+    }
+
+    pub fn set(&mut self, name: &Token<'src>, value: Value<'src>) {
+        self.fields.insert(name._string.to_string(), value);
+    }
+}
+
+// fields can hold the instance itself, so only the class name is printed
+impl fmt::Debug for Instance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Instance({})", self.class.name)
+    }
+}
+/// Everything that can go wrong while a program runs. Each variant carries
+/// what's needed to explain it, and renders to the same text jlox prints.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeErrorKind {
+    /// An operator was applied to values it doesn't accept. `left` is `None`
+    /// for the prefix operators, which only have a right operand.
+    TypeMismatch {
+        operator: String,
+        left: Option<&'static str>,
+        right: &'static str,
+    },
+    UndefinedVariable(String),
+    UndefinedProperty(String),
+    ArityMismatch {
+        expected: usize,
+        got: usize,
+    },
+    /// Holds the type of the value that was called.
+    NotCallable(&'static str),
+    /// A property was read off something that isn't an instance.
+    NoProperties(&'static str),
+    /// A field was assigned on something that isn't an instance.
+    NoFields(&'static str),
+    SuperclassNotClass(&'static str),
+    InvalidAssignmentTarget,
+    /// Too many calls were running at once.
+    StackOverflow,
 }
+
+impl fmt::Display for RuntimeErrorKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeErrorKind::TypeMismatch { left: None, .. } => {
+                write!(fmt, "Operand must be a number.")
+            }
+            RuntimeErrorKind::TypeMismatch { operator, .. } if operator == "+" => {
+                write!(fmt, "Operands must be two numbers or two strings.")
+            }
+            RuntimeErrorKind::TypeMismatch { .. } => write!(fmt, "Operands must be numbers."),
+            RuntimeErrorKind::UndefinedVariable(name) => {
+                write!(fmt, "Undefined variable '{}'.", name)
+            }
+            RuntimeErrorKind::UndefinedProperty(name) => {
+                write!(fmt, "Undefined property '{}'.", name)
+            }
+            RuntimeErrorKind::ArityMismatch { expected, got } => {
+                write!(fmt, "Expected {} arguments but got {}.", expected, got)
+            }
+            RuntimeErrorKind::NotCallable(_) => write!(fmt, "Can only call functions and classes."),
+            RuntimeErrorKind::NoProperties(_) => write!(fmt, "Only instances have properties."),
+            RuntimeErrorKind::NoFields(_) => write!(fmt, "Only instances have fields."),
+            RuntimeErrorKind::SuperclassNotClass(_) => write!(fmt, "Superclass must be a class."),
+            RuntimeErrorKind::InvalidAssignmentTarget => write!(
+                fmt,
+                "Invalid assignment target. Left-hand side must be an identifier."
+            ),
+            RuntimeErrorKind::StackOverflow => write!(fmt, "Stack overflow."),
+        }
+    }
+}
+
+/// A call that a runtime error unwound out of: the function that was running
+/// and the line it had been called from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallFrame {
+    pub function: String,
+    pub call_line: u32,
+}
+
+/// A runtime error as the evaluator raises it. It becomes a `Diagnostic`
+/// once it reaches the top of the program.
 #[derive(Debug)]
 pub struct RuntimeError {
-    pub msg: String,
-    //for now set line to juss 1
-    pub line: u32,
-    pub exit: i32,
+    pub kind: RuntimeErrorKind,
+    pub span: Span,
+    /// Calls the error has propagated out of so far, innermost first.
+    pub trace: Vec<CallFrame>,
 }
 
 impl RuntimeError {
-    pub fn new(msg: String, line: u32) -> Self {
+    pub fn new(kind: RuntimeErrorKind, span: Span) -> Self {
         Self {
-            msg,
-            line,
-            exit: 70,
+            kind,
+            span,
+            trace: Vec::new(),
         }
     }
 }
 
-impl fmt::Display for RuntimeError {
-    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(fmt, "{}\n[line {}]", self.msg, self.line)
+impl<'src> Value<'src> {
+    /// Lox truthiness: `nil` and `false` are falsey, every other value is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// Name of the value's type, as used in runtime errors.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Number(_) => "number",
+            Value::Nil => "nil",
+            Value::Bool(_) => "boolean",
+            Value::Function(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+        }
     }
 }
 
-impl std::fmt::Display for Value {
+/// Lox equality, which never fails: values of different kinds are simply
+/// unequal, `nil` equals only itself, and functions, classes and instances
+/// are compared by identity rather than by contents.
+impl PartialEq for Value<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Value<'_> {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Value::String(s) => write!(fmt, "{}", s),
             Value::Nil => write!(fmt, "nil"),
+            // shortest form that reads back as the same f64, so `3` rather
+            // than `3.0` and `0.30000000000000004` rather than a rounded guess
+            Value::Number(n) if n.is_infinite() => crate::write_infinity(fmt, *n),
             Value::Number(n) => write!(fmt, "{}", n),
             Value::Bool(b) => write!(fmt, "{}", b),
+            Value::Function(function) => {
+                write!(fmt, "<fn {}>", function.declaration.name._string)
+            }
+            Value::Class(class) => write!(fmt, "{}", class.name),
+            Value::Instance(instance) => write!(fmt, "{} instance", instance.borrow().class.name),
         }
     }
 }
 
-impl Evaluator {
+impl<'src> Evaluator<'src> {
     pub fn new() -> Self {
+        let globals = Environment::new().into_ref();
         Self {
-            line: 0,
-            symbols: HashMap::new(),
+            environment: globals.clone(),
+            globals,
+            locals: HashMap::new(),
+            calls: 0,
+        }
+    }
+
+    /// Declares `name` in the current scope: in the slot the resolver gave
+    /// it if it's a local, by name if it's a global.
+    pub fn define(&mut self, name: &Token<'src>, value: Value<'src>) {
+        let mut environment = self.environment.borrow_mut();
+        match self.locals.get(&name._span.start) {
+            Some(binding) => environment.define_at(binding.slot, value),
+            None => environment.define(name._string.to_string(), value),
         }
     }
 
-    pub fn assign(&mut self, name: &String, right: Value) -> Result<Value, RuntimeError> {
-        if self.symbols.contains_key(name) {
-            self.symbols.insert(name.clone(), right.clone());
+    pub fn assign(
+        &mut self,
+        name: &str,
+        right: Value<'src>,
+        span: Span,
+    ) -> Result<Value<'src>, RuntimeError> {
+        let assigned = match self.locals.get(&span.start) {
+            Some(&binding) => Environment::assign_at(&self.environment, binding, right.clone()),
+            None => self.globals.borrow_mut().assign(name, right.clone()),
+        };
+        if assigned {
             Ok(right)
         } else {
             Err(RuntimeError::new(
-                format!("invalid use of undeclared indentifier {right}"),
-                self.line,
+                RuntimeErrorKind::UndefinedVariable(name.to_string()),
+                span,
             ))
         }
     }
 
-    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
-        match expr {
-            Expr::Literal(v) => Ok(Self::EvaluateLiteral(self, &v)?),
-            Expr::Grouping(expr) => Self::evaluate(self, expr),
-            Expr::Unary(op, expr) => Self::EvalUnary(self, op, expr),
-            Expr::Assignment(left, right) => {
+    pub fn evaluate(&mut self, expr: &Expr<'src>) -> Result<Value<'src>, RuntimeError> {
+        match &expr.kind {
+            ExprKind::Literal(v) => Ok(Self::EvaluateLiteral(self, v, expr.span)?),
+            ExprKind::Grouping(expr) => Self::evaluate(self, expr),
+            ExprKind::Unary(op, expr) => Self::EvalUnary(self, op, expr),
+            ExprKind::Assignment(left, right) => {
                 // `left` should be an identifier, so we expect an ExprLiteral::Identifier.
                 // Make sure the left side is a valid identifier.
-                if let Expr::Literal(ExprLiteral::Identifier(ref name)) = **left {
+                if let ExprKind::Literal(ExprLiteral::Identifier(name)) = left.kind {
                     // Evaluate the right-hand expression.
                     let value = self.evaluate(right)?;
 
-                    // Now assign the evaluated value to the identifier in `symbols`.
-                    self.assign(name, value.clone())?;
+                    // Now assign the evaluated value to the nearest scope declaring it.
+                    self.assign(name, value.clone(), left.span)?;
 
                     // Return the assigned value.
                     Ok(value)
                 } else {
                     // If the left-hand side is not an identifier, return an error.
                     Err(RuntimeError::new(
-                        String::from(
-                            "Invalid assignment target. Left-hand side must be an identifier.",
-                        ),
-                        self.line,
+                        RuntimeErrorKind::InvalidAssignmentTarget,
+                        left.span,
                     ))
                 }
             }
-            Expr::Binary(op, left, right) => Self::EvalBinary(self, op, left, right),
+            ExprKind::Binary(op, left, right) => Self::EvalBinary(self, op, left, right),
+            ExprKind::Logical(op, left, right) => self.eval_logical(op, left, right),
+            ExprKind::Call(callee, paren, arguments) => {
+                let callee = self.evaluate(callee)?;
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.evaluate(argument))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call(callee, arguments, paren)
+            }
+            ExprKind::Get(object, name) => match self.evaluate(object)? {
+                Value::Instance(instance) => Instance::get(&instance, name),
+                other => Err(RuntimeError::new(
+                    RuntimeErrorKind::NoProperties(other.type_name()),
+                    name._span,
+                )),
+            },
+            ExprKind::Set(object, name, value) => {
+                let object = self.evaluate(object)?;
+                let Value::Instance(instance) = object else {
+                    return Err(RuntimeError::new(
+                        RuntimeErrorKind::NoFields(object.type_name()),
+                        name._span,
+                    ));
+                };
+                let value = self.evaluate(value)?;
+                instance.borrow_mut().set(name, value.clone());
+                Ok(value)
+            }
+            ExprKind::This(keyword) => self.look_up(keyword._string, keyword._span),
+            ExprKind::Super(keyword, method) => {
+                let Value::Class(superclass) = self.look_up("super", keyword._span)? else {
+                    unreachable!("'super' is only ever bound to a class")
+                };
+                // `this` lives in the scope just inside the one holding `super`
+                let this = self
+                    .locals
+                    .get(&keyword._span.start)
+                    .and_then(|binding| {
+                        let this = Binding {
+                            depth: binding.depth.saturating_sub(1),
+                            slot: 0,
+                        };
+                        Environment::get_at(&self.environment, this)
+                    })
+                    .unwrap_or(Value::Nil);
+                match superclass.find_method(method._string) {
+                    Some(found) => Ok(Value::Function(Rc::new(found.bind(this)))),
+                    None => Err(RuntimeError::new(
+                        RuntimeErrorKind::UndefinedProperty(method._string.to_string()),
+                        method._span,
+                    )),
+                }
+            }
+            ExprKind::Interpolation(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    // the same text `print` would show for it
+                    text.push_str(&self.evaluate(part)?.to_string());
+                }
+                Ok(Value::String(text))
+            }
             _ => unreachable!(),
         }
     }
 
     pub fn EvalBinary(
         &mut self,
-        op: &Token,
-        left: &Box<Expr>,
-        right: &Box<Expr>,
-    ) -> Result<Value, RuntimeError> {
+        op: &Token<'src>,
+        left: &Box<Expr<'src>>,
+        right: &Box<Expr<'src>>,
+    ) -> Result<Value<'src>, RuntimeError> {
         let left = Self::evaluate(self, left)?;
         let right = Self::evaluate(self, right)?;
 
-        match op._string.as_str() {
-            "*" => {
-                if let Value::Number(lhs) = left {
-                    let mut rhs: f32 = 0.;
-                    if let Value::Number(r) = right {
-                        rhs = r;
-                    } else {
-                        return Err(RuntimeError::new(
-                            String::from("Operands must be numbers."),
-                            self.line,
-                        ));
-                    }
-                    return Ok(Value::Number(lhs * rhs));
-                } else {
-                    Err(RuntimeError::new(
-                        String::from("Operands must be numbers."),
-                        self.line,
-                    ))
-                }
-            }
-            "/" => {
-                if let Value::Number(lhs) = left {
-                    let mut rhs: f32 = 0.;
-                    if let Value::Number(r) = right {
-                        rhs = r;
-                    } else {
-                        return Err(RuntimeError::new(
-                            String::from("Operands must be numbers."),
-                            self.line,
-                        ));
-                    }
-                    return Ok(Value::Number(lhs / rhs));
-                } else {
-                    Err(RuntimeError::new(
-                        String::from("Operands must be numbers."),
-                        self.line,
-                    ))
-                }
-            }
-            "+" => {
-                if let Value::Number(lhs) = left {
-                    let mut rhs: f32 = 0.;
-                    if let Value::Number(r) = right {
-                        rhs = r;
-                    } else {
-                        return Err(RuntimeError::new(
-                            String::from("Operands must be numbers."),
-                            self.line,
-                        ));
-                    }
-                    return Ok(Value::Number(lhs + rhs));
-                } else {
-                    if let Value::String(lhs) = left {
-                        let mut rhs = String::new();
-                        if let Value::String(r) = right {
-                            rhs = r;
-                        } else {
-                            return Err(RuntimeError::new(
-                                String::from("Operands must be numbers."),
-                                self.line,
-                            ));
-                        }
-                        Ok(Value::String(format!("{}{}", lhs, rhs)))
-                    } else {
-                        Err(RuntimeError::new(
-                            String::from("Operands must be numbers."),
-                            self.line,
-                        ))
-                    }
-                }
-            }
-            "-" => {
-                if let Value::Number(lhs) = left {
-                    let mut rhs: f32 = 0.;
-                    if let Value::Number(r) = right {
-                        rhs = r;
-                    } else {
-                        return Err(RuntimeError::new(
-                            String::from("Operands must be numbers."),
-                            self.line,
-                        ));
-                    }
-                    return Ok(Value::Number(lhs - rhs));
-                } else {
-                    Err(RuntimeError::new(
-                        String::from("Operands must be numbers."),
-                        self.line,
-                    ))
-                }
-            }
-            "<" => {
-                if let Value::Number(lhs) = left {
-                    let mut rhs: f32 = 0.;
-                    if let Value::Number(r) = right {
-                        rhs = r;
-                    } else {
-                        return Err(RuntimeError::new(
-                            String::from("Operands must be numbers."),
-                            self.line,
-                        ));
-                    }
-                    return Ok(Value::Bool((lhs < rhs)));
-                } else {
-                    Err(RuntimeError::new(
-                        String::from("Operands must be numbers."),
-                        self.line,
-                    ))
-                }
-            }
-            ">" => {
-                if let Value::Number(lhs) = left {
-                    let mut rhs: f32 = 0.;
-                    if let Value::Number(r) = right {
-                        rhs = r;
-                    } else {
-                        return Err(RuntimeError::new(
-                            String::from("Operands must be numbers."),
-                            self.line,
-                        ));
-                    }
-                    return Ok(Value::Bool((lhs > rhs)));
-                } else {
-                    Err(RuntimeError::new(
-                        String::from("Operands must be numbers."),
-                        self.line,
-                    ))
-                }
-            }
-            ">=" => {
-                if let Value::Number(lhs) = left {
-                    let mut rhs: f32 = 0.;
-                    if let Value::Number(r) = right {
-                        rhs = r;
-                    } else {
-                        return Err(RuntimeError::new(
-                            String::from("Operands must be numbers."),
-                            self.line,
-                        ));
-                    }
-                    return Ok(Value::Bool((lhs >= rhs)));
-                } else {
-                    Err(RuntimeError::new(
-                        String::from("Operands must be numbers."),
-                        self.line,
-                    ))
-                }
+        // equality is defined for every pair of values, so it can't mismatch
+        match op._string {
+            "==" => return Ok(Value::Bool(left == right)),
+            "!=" => return Ok(Value::Bool(left != right)),
+            _ => {}
+        }
+
+        let (lhs, rhs) = match (&left, &right, op._string) {
+            (Value::Number(lhs), Value::Number(rhs), _) => (*lhs, *rhs),
+            (Value::String(lhs), Value::String(rhs), "+") => {
+                return Ok(Value::String(format!("{}{}", lhs, rhs)))
             }
-            "<=" => {
-                if let Value::Number(lhs) = left {
-                    let mut rhs: f32 = 0.;
-                    if let Value::Number(r) = right {
-                        rhs = r;
-                    } else {
-                        return Err(RuntimeError::new(
-                            String::from("Operands must be numbers."),
-                            self.line,
-                        ));
-                    }
-                    return Ok(Value::Bool((lhs <= rhs)));
-                } else {
-                    Err(RuntimeError::new(
-                        String::from("Operands must be numbers."),
-                        self.line,
-                    ))
-                }
+            _ => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::TypeMismatch {
+                        operator: op._string.to_string(),
+                        left: Some(left.type_name()),
+                        right: right.type_name(),
+                    },
+                    op._span,
+                ))
             }
-            "==" => {
-                if let Value::Number(lhs) = left {
-                    let mut rhs: f32 = 0.;
-                    if let Value::Number(r) = right {
-                        rhs = r;
-                    } else {
-                        return Ok(Value::Bool(false));
-                    }
-                    return Ok(Value::Bool((lhs == rhs)));
-                } else if let Value::Bool(lhs) = left {
-                    let mut rhs = false;
-                    if let Value::Bool(r) = right {
-                        rhs = r;
-                    } else {
-                        return Ok(Value::Bool(false));
-                    }
-                    return Ok(Value::Bool((lhs == rhs)));
-                } else {
-                    if let Value::String(lhs) = left {
-                        let mut rhs = String::new();
-                        if let Value::String(r) = right {
-                            rhs = r;
-                        } else {
-                            return Ok(Value::Bool(false));
-                        }
-                        Ok(Value::Bool((lhs == rhs)))
-                    } else {
-                        Err(RuntimeError::new(
-                            String::from("Operands must be numbers."),
-                            self.line,
-                        ))
-                    }
-                }
+        };
+
+        match op._string {
+            "*" => Ok(Value::Number(lhs * rhs)),
+            "/" => Ok(Value::Number(lhs / rhs)),
+            "+" => Ok(Value::Number(lhs + rhs)),
+            "-" => Ok(Value::Number(lhs - rhs)),
+            "<" => Ok(Value::Bool(lhs < rhs)),
+            ">" => Ok(Value::Bool(lhs > rhs)),
+            ">=" => Ok(Value::Bool(lhs >= rhs)),
+            "<=" => Ok(Value::Bool(lhs <= rhs)),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn call(
+        &mut self,
+        callee: Value<'src>,
+        arguments: Vec<Value<'src>>,
+        paren: &Token<'src>,
+    ) -> Result<Value<'src>, RuntimeError> {
+        let arity = match &callee {
+            Value::Function(function) => function.declaration.params.len(),
+            Value::Class(class) => class.arity(),
+            _ => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::NotCallable(callee.type_name()),
+                    paren._span,
+                ))
             }
-            "!=" => {
-                if let Value::Number(lhs) = left {
-                    let mut rhs: f32 = 0.;
-                    if let Value::Number(r) = right {
-                        rhs = r;
-                    } else {
-                        return Ok(Value::Bool(false));
-                    }
-                    return Ok(Value::Bool((lhs != rhs)));
-                }
-                if let Value::Bool(lhs) = left {
-                    let mut rhs = false;
-                    if let Value::Bool(r) = right {
-                        rhs = r;
-                    } else {
-                        return Ok(Value::Bool(false));
-                    }
-                    return Ok(Value::Bool((lhs != rhs)));
-                } else {
-                    if let Value::String(lhs) = left {
-                        let mut rhs = String::new();
-                        if let Value::String(r) = right {
-                            rhs = r;
-                        } else {
-                            return Ok(Value::Bool(false));
-                        }
-                        Ok(Value::Bool((lhs != rhs)))
-                    } else {
-                        Err(RuntimeError::new(
-                            String::from("Operands must be numbers."),
-                            self.line,
-                        ))
-                    }
+        };
+        if arguments.len() != arity {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::ArityMismatch {
+                    expected: arity,
+                    got: arguments.len(),
+                },
+                paren._span,
+            ));
+        }
+
+        match callee {
+            Value::Function(function) => self.call_function(&function, arguments, paren),
+            Value::Class(class) => {
+                let instance = Value::Instance(Rc::new(RefCell::new(Instance::new(class.clone()))));
+                if let Some(init) = class.find_method("init") {
+                    self.call_function(&init.bind(instance.clone()), arguments, paren)?;
                 }
+                Ok(instance)
             }
             _ => unreachable!(),
         }
     }
 
-    pub fn EvalUnary(&mut self, op: &Token, expr: &Box<Expr>) -> Result<Value, RuntimeError> {
+    fn call_function(
+        &mut self,
+        function: &Function<'src>,
+        arguments: Vec<Value<'src>>,
+        paren: &Token<'src>,
+    ) -> Result<Value<'src>, RuntimeError> {
+        let declaration = &function.declaration;
+        if self.calls >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::StackOverflow,
+                paren._span,
+            ));
+        }
+
+        // parameters live in a fresh scope hanging off the closure, not the caller's scope
+        let mut environment = Environment::with_enclosing(function.closure.clone());
+        for (slot, argument) in arguments.into_iter().enumerate() {
+            environment.define_at(slot, argument);
+        }
+
+        self.calls += 1;
+        let flow = self.execute_block(&declaration.body, environment);
+        self.calls -= 1;
+        let flow = flow.map_err(|mut error| {
+            error.trace.push(CallFrame {
+                function: declaration.name._string.to_string(),
+                call_line: paren._span.line,
+            });
+            error
+        })?;
+        let value = match flow {
+            Flow::Return(value) => value,
+            Flow::Normal => Value::Nil,
+        };
+        // an initializer always hands back the instance, even on a bare `return;`
+        if function.is_initializer {
+            let this = Binding { depth: 0, slot: 0 };
+            return Ok(Environment::get_at(&function.closure, this).unwrap_or(Value::Nil));
+        }
+        Ok(value)
+    }
+
+    fn look_up(&self, name: &str, span: Span) -> Result<Value<'src>, RuntimeError> {
+        let value = match self.locals.get(&span.start) {
+            Some(&binding) => Environment::get_at(&self.environment, binding),
+            None => self.globals.borrow().get(name),
+        };
+        value.ok_or_else(|| {
+            RuntimeError::new(RuntimeErrorKind::UndefinedVariable(name.to_string()), span)
+        })
+    }
+
+    // returns whichever operand decided the result, the right side is only
+    // evaluated when the left one doesn't already settle it
+    pub fn eval_logical(
+        &mut self,
+        op: &Token<'src>,
+        left: &Expr<'src>,
+        right: &Expr<'src>,
+    ) -> Result<Value<'src>, RuntimeError> {
+        let left = self.evaluate(left)?;
+        let decided = match op._type {
+            TokenType::or => left.is_truthy(),
+            _ => !left.is_truthy(),
+        };
+        if decided {
+            Ok(left)
+        } else {
+            self.evaluate(right)
+        }
+    }
+
+    pub fn EvalUnary(
+        &mut self,
+        op: &Token<'src>,
+        expr: &Box<Expr<'src>>,
+    ) -> Result<Value<'src>, RuntimeError> {
         let right = Self::evaluate(self, expr)?;
-        match op._string.as_str() {
+        match op._string {
             "-" => {
                 if let Value::Number(n) = right {
                     Ok(Value::Number(-n))
                 } else {
                     Err(RuntimeError::new(
-                        String::from("Operand must be an number."),
-                        self.line,
+                        RuntimeErrorKind::TypeMismatch {
+                            operator: op._string.to_string(),
+                            left: None,
+                            right: right.type_name(),
+                        },
+                        op._span,
                     ))
                 }
             }
-            "!" => match right {
-                Value::Nil => Ok(Value::Bool(true)),
-                Value::Bool(b) => {
-                    if b {
-                        Ok(Value::Bool(false))
-                    } else {
-                        Ok(Value::Bool(true))
-                    }
-                }
-                Value::Number(n) => Ok(Value::Bool(n == 0.0)),
-                Value::String(s) => Ok(Value::Bool(s.is_empty())),
-            },
+            "!" => Ok(Value::Bool(!right.is_truthy())),
             _ => unreachable!(),
         }
     }
 
-    pub fn EvaluateLiteral(&mut self, literal: &ExprLiteral) -> Result<Value, RuntimeError> {
+    pub fn EvaluateLiteral(
+        &mut self,
+        literal: &ExprLiteral<'src>,
+        span: Span,
+    ) -> Result<Value<'src>, RuntimeError> {
         match literal {
             ExprLiteral::Bool(truthy) => Ok(Value::Bool(*truthy)),
-            ExprLiteral::String(Stringy) => Ok(Value::String(Stringy.to_owned())),
-            ExprLiteral::Number(numy) => {
-                let f32 = numy.parse::<f32>().unwrap();
-                Ok(Value::Number(f32))
+            ExprLiteral::String(Stringy) => Ok(Value::String(Stringy.to_string())),
+            ExprLiteral::Number(numy) => Ok(Value::Number(*numy)),
+            ExprLiteral::Identifier(str) => self.look_up(str, span),
+            ExprLiteral::Nil => Ok(Value::Nil),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::Lexer;
+    use crate::parse::Parser;
+    use crate::resolve::Resolver;
+    use crate::smnt::FunctionDecl;
+
+    fn function(name: &'static str) -> Value<'static> {
+        let declaration = FunctionDecl {
+            name: Token::newToken(TokenType::Identifer, name, None),
+            params: Vec::new(),
+            body: Vec::new(),
+        };
+        Value::Function(Rc::new(Function {
+            declaration: Rc::new(declaration),
+            closure: Environment::new().into_ref(),
+            is_initializer: false,
+        }))
+    }
+
+    fn class(name: &str) -> Rc<Class<'static>> {
+        Rc::new(Class {
+            name: name.to_string(),
+            superclass: None,
+            methods: HashMap::new(),
+        })
+    }
+
+    fn instance(class: &Rc<Class<'static>>) -> Value<'static> {
+        Value::Instance(Rc::new(RefCell::new(Instance::new(class.clone()))))
+    }
+
+    fn evaluate(source: &str) -> Result<Value<'_>, RuntimeError> {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.by_ref().collect();
+        assert!(lexer.errors.is_empty());
+        let expr = Parser::new(tokens).parse().unwrap();
+        Evaluator::new().evaluate(&expr)
+    }
+
+    // runs a whole script and hands back whatever it left in `result`
+    fn run(source: &str) -> Result<Value<'_>, RuntimeError> {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.by_ref().collect();
+        assert!(lexer.errors.is_empty());
+        let statments = Parser::new(tokens).stmt_parser().unwrap();
+        let mut evaluator = Evaluator::new();
+        evaluator.locals = Resolver::new().resolve(&statments).unwrap();
+        for statment in &statments {
+            evaluator.execute(statment)?;
+        }
+        let result = evaluator.globals.borrow().get("result");
+        Ok(result.unwrap_or(Value::Nil))
+    }
+
+    #[test]
+    fn equality_is_defined_for_every_pair_of_values() {
+        let f = function("f");
+        let point = class("Point");
+        let p = instance(&point);
+        let string = |s: &str| Value::String(s.to_string());
+
+        let cases = [
+            (Value::Nil, Value::Nil, true),
+            (Value::Nil, Value::Bool(false), false),
+            (Value::Nil, Value::Number(0.0), false),
+            (Value::Nil, string(""), false),
+            (Value::Bool(true), Value::Bool(true), true),
+            (Value::Bool(true), Value::Bool(false), false),
+            (Value::Bool(true), Value::Number(1.0), false),
+            (Value::Bool(false), string("false"), false),
+            (Value::Number(1.0), Value::Number(1.0), true),
+            (Value::Number(1.0), Value::Number(2.0), false),
+            (Value::Number(0.0), Value::Number(-0.0), true),
+            (Value::Number(f64::NAN), Value::Number(f64::NAN), false),
+            (Value::Number(1.0), string("1"), false),
+            (string("a"), string("a"), true),
+            (string("a"), string("b"), false),
+            (f.clone(), f.clone(), true),
+            (f.clone(), function("f"), false),
+            (f.clone(), Value::Nil, false),
+            (
+                Value::Class(point.clone()),
+                Value::Class(point.clone()),
+                true,
+            ),
+            (
+                Value::Class(point.clone()),
+                Value::Class(class("Point")),
+                false,
+            ),
+            (Value::Class(point.clone()), p.clone(), false),
+            (p.clone(), p.clone(), true),
+            (p.clone(), instance(&point), false),
+            (p.clone(), string("Point instance"), false),
+        ];
+
+        for (left, right, expected) in cases {
+            assert_eq!(left == right, expected, "{left:?} == {right:?}");
+            assert_eq!(right == left, expected, "{right:?} == {left:?}");
+            assert_eq!(left != right, !expected, "{left:?} != {right:?}");
+        }
+    }
+
+    #[test]
+    fn equality_operators_never_raise() {
+        let cases = [
+            ("nil == nil", true),
+            ("nil != nil", false),
+            ("nil == false", false),
+            ("nil != false", true),
+            ("1 == 1", true),
+            ("1 == \"1\"", false),
+            ("1 != \"1\"", true),
+            ("\"a\" == \"a\"", true),
+            ("true == 1", false),
+            ("true != nil", true),
+            ("(1 + 2) == 3", true),
+        ];
+
+        for (source, expected) in cases {
+            match evaluate(source) {
+                Ok(value) => assert_eq!(value, Value::Bool(expected), "{source}"),
+                Err(e) => panic!("{source} raised {}", e.kind),
             }
-            ExprLiteral::Identifier(str) => {
-                let val = self.symbols.get(str);
-                if val.is_none() {
-                    return Err(RuntimeError::new(
-                        format!("Undefined variable '{}'.", str),
-                        self.line,
-                    ));
+        }
+    }
+
+    #[test]
+    fn numbers_print_like_lox() {
+        let cases = [
+            ("3", "3"),
+            ("-0.5", "-0.5"),
+            ("0.1 + 0.2", "0.30000000000000004"),
+            ("1 / 0", "Infinity"),
+            ("-1 / 0", "-Infinity"),
+            ("0 / 0", "NaN"),
+        ];
+
+        for (source, expected) in cases {
+            match evaluate(source) {
+                Ok(value) => assert_eq!(value.to_string(), expected, "{source}"),
+                Err(e) => panic!("{source} raised {}", e.kind),
+            }
+        }
+    }
+
+    #[test]
+    fn interpolation_stringifies_each_part_like_print() {
+        let cases = [
+            ("\"n=${1}\"", "n=1"),
+            ("\"${1 + 2} items\"", "3 items"),
+            ("\"${nil}, ${true}, ${0.5}\"", "nil, true, 0.5"),
+            ("\"a ${\"b ${\"c\"} d\"} e\"", "a b c d e"),
+            ("\"${1}${2}\"", "12"),
+            ("\"\\${1} costs $5 {}\"", "${1} costs $5 {}"),
+        ];
+
+        for (source, expected) in cases {
+            match evaluate(source) {
+                Ok(value) => assert_eq!(value, Value::String(expected.to_string()), "{source}"),
+                Err(e) => panic!("{source} raised {}", e.kind),
+            }
+        }
+    }
+
+    #[test]
+    fn runtime_errors_carry_a_matchable_kind() {
+        let cases = [
+            (
+                "1 + \"a\"",
+                RuntimeErrorKind::TypeMismatch {
+                    operator: "+".to_string(),
+                    left: Some("number"),
+                    right: "string",
+                },
+                "Operands must be two numbers or two strings.",
+            ),
+            (
+                "nil < 1",
+                RuntimeErrorKind::TypeMismatch {
+                    operator: "<".to_string(),
+                    left: Some("nil"),
+                    right: "number",
+                },
+                "Operands must be numbers.",
+            ),
+            (
+                "-true",
+                RuntimeErrorKind::TypeMismatch {
+                    operator: "-".to_string(),
+                    left: None,
+                    right: "boolean",
+                },
+                "Operand must be a number.",
+            ),
+            (
+                "missing",
+                RuntimeErrorKind::UndefinedVariable("missing".to_string()),
+                "Undefined variable 'missing'.",
+            ),
+            (
+                "\"f\"()",
+                RuntimeErrorKind::NotCallable("string"),
+                "Can only call functions and classes.",
+            ),
+            (
+                "(1).x",
+                RuntimeErrorKind::NoProperties("number"),
+                "Only instances have properties.",
+            ),
+        ];
+
+        for (source, kind, text) in cases {
+            match evaluate(source) {
+                Ok(value) => panic!("{source} evaluated to {value}"),
+                Err(e) => {
+                    assert_eq!(e.kind, kind, "{source}");
+                    assert_eq!(e.kind.to_string(), text, "{source}");
                 }
-                Ok(val.unwrap().clone())
             }
-            ExprLiteral::Nil => Ok(Value::Nil),
         }
     }
+
+    #[test]
+    fn functions_take_arguments_and_return_values() {
+        let string = |s: &str| Value::String(s.to_string());
+        let cases = [
+            (
+                "fun add(a, b) { return a + b; } var result = add(1, 2);",
+                Value::Number(3.0),
+            ),
+            ("fun f() {} var result = f();", Value::Nil),
+            ("fun f() { return; print 1; } var result = f();", Value::Nil),
+            (
+                "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+                 var result = fib(10);",
+                Value::Number(55.0),
+            ),
+            (
+                "fun f(a) { a = a + 1; return a; } var result = 1; f(result);",
+                Value::Number(1.0),
+            ),
+            (
+                "var result = \"outer\"; fun f() { var result = \"inner\"; } f();",
+                string("outer"),
+            ),
+            (
+                "fun f() { while (true) { return \"out\"; } } var result = f();",
+                string("out"),
+            ),
+        ];
+
+        for (source, expected) in cases {
+            match run(source) {
+                Ok(value) => assert_eq!(value, expected, "{source}"),
+                Err(e) => panic!("{source} raised {}", e.kind),
+            }
+        }
+    }
+
+    #[test]
+    fn closures_keep_their_scope_alive() {
+        let string = |s: &str| Value::String(s.to_string());
+        let cases = [
+            (
+                "fun counter() { var n = 0; fun count() { n = n + 1; return n; } return count; }
+                 var c = counter(); c(); var result = c();",
+                Value::Number(2.0),
+            ),
+            (
+                "fun counter() { var n = 0; fun count() { n = n + 1; return n; } return count; }
+                 var a = counter(); var b = counter(); a(); a(); var result = b();",
+                Value::Number(1.0),
+            ),
+            (
+                "fun adder(n) { fun add(m) { return n + m; } return add; }
+                 var result = adder(1)(2);",
+                Value::Number(3.0),
+            ),
+            (
+                "var f; { var x = \"block\"; fun g() { return x; } f = g; }
+                 var result = f();",
+                string("block"),
+            ),
+            (
+                "var a = \"global\"; var result = \"\";
+                 { fun show() { result = result + a; } show(); var a = \"block\"; show(); }",
+                string("globalglobal"),
+            ),
+        ];
+
+        for (source, expected) in cases {
+            match run(source) {
+                Ok(value) => assert_eq!(value, expected, "{source}"),
+                Err(e) => panic!("{source} raised {}", e.kind),
+            }
+        }
+    }
+
+    #[test]
+    fn classes_make_instances_with_fields_and_methods() {
+        let cases = [
+            (
+                "class P { init(x) { this.x = x; } } var result = P(3).x;",
+                Value::Number(3.0),
+            ),
+            (
+                "class A { init() { this.v = 1; } } var a = A(); var result = a.init() == a;",
+                Value::Bool(true),
+            ),
+            (
+                "class A { init() { this.v = 1; return; } } var a = A(); var result = a.init() == a;",
+                Value::Bool(true),
+            ),
+            (
+                "class C { m() { return this.v; } } var c = C(); c.v = 5; var m = c.m;
+                 c.v = 6; var result = m();",
+                Value::Number(6.0),
+            ),
+            (
+                "class C { m() { return this; } } var a = C(); var b = C(); b.m = a.m;
+                 var result = b.m() == a;",
+                Value::Bool(true),
+            ),
+            (
+                "class C { m() { return 1; } } var c = C(); c.m = 2; var result = c.m;",
+                Value::Number(2.0),
+            ),
+        ];
+
+        for (source, expected) in cases {
+            match run(source) {
+                Ok(value) => assert_eq!(value, expected, "{source}"),
+                Err(e) => panic!("{source} raised {}", e.kind),
+            }
+        }
+
+        let error = run("class A { init(a) {} } A();").unwrap_err();
+        let arity = RuntimeErrorKind::ArityMismatch {
+            expected: 1,
+            got: 0,
+        };
+        assert_eq!(error.kind, arity);
+        let error = run("class A {} A().x;").unwrap_err();
+        assert_eq!(
+            error.kind,
+            RuntimeErrorKind::UndefinedProperty("x".to_string())
+        );
+    }
+
+    #[test]
+    fn subclasses_inherit_and_call_up_with_super() {
+        let string = |s: &str| Value::String(s.to_string());
+        let cases = [
+            (
+                "class A { m() { return \"A\"; } } class B < A {} var result = B().m();",
+                string("A"),
+            ),
+            (
+                "class A { init(v) { this.v = v; } } class B < A {} var result = B(4).v;",
+                Value::Number(4.0),
+            ),
+            (
+                "class A { name() { return this.n; } }
+                 class B < A { name() { return \"B\" + super.name(); } }
+                 var b = B(); b.n = \"x\"; var result = b.name();",
+                string("Bx"),
+            ),
+            (
+                "class A { m() { return \"A\"; } }
+                 class B < A { m() { return \"B\"; } test() { return super.m(); } }
+                 class C < B { m() { return \"C\"; } }
+                 var result = C().test();",
+                string("A"),
+            ),
+            (
+                "class A { m() { return this; } }
+                 class B < A { m() { var up = super.m; return up(); } }
+                 var b = B(); var result = b.m() == b;",
+                Value::Bool(true),
+            ),
+        ];
+
+        for (source, expected) in cases {
+            match run(source) {
+                Ok(value) => assert_eq!(value, expected, "{source}"),
+                Err(e) => panic!("{source} raised {}", e.kind),
+            }
+        }
+
+        let error = run("var A = 1; class B < A {}").unwrap_err();
+        assert_eq!(error.kind, RuntimeErrorKind::SuperclassNotClass("number"));
+        let error = run("class A {} class B < A { m() { super.m(); } } B().m();").unwrap_err();
+        assert_eq!(
+            error.kind,
+            RuntimeErrorKind::UndefinedProperty("m".to_string())
+        );
+    }
+
+    #[test]
+    fn bad_calls_are_runtime_errors() {
+        let cases = [
+            (
+                "fun f(a, b) {} f(1);",
+                RuntimeErrorKind::ArityMismatch {
+                    expected: 2,
+                    got: 1,
+                },
+                "Expected 2 arguments but got 1.",
+            ),
+            (
+                "fun f() {} f(1, 2);",
+                RuntimeErrorKind::ArityMismatch {
+                    expected: 0,
+                    got: 2,
+                },
+                "Expected 0 arguments but got 2.",
+            ),
+            (
+                "var f = nil; f();",
+                RuntimeErrorKind::NotCallable("nil"),
+                "Can only call functions and classes.",
+            ),
+        ];
+
+        for (source, kind, text) in cases {
+            match run(source) {
+                Ok(value) => panic!("{source} ran to {value}"),
+                Err(e) => {
+                    assert_eq!(e.kind, kind, "{source}");
+                    assert_eq!(e.kind.to_string(), text, "{source}");
+                }
+            }
+        }
+    }
+
+    // on a thread with the main thread's stack, since test threads get less
+    #[test]
+    fn runaway_recursion_is_a_stack_overflow() {
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(runaway_recursion)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    fn runaway_recursion() {
+        let error = run("fun f(n) { return f(n + 1); }\nf(0);").unwrap_err();
+        assert_eq!(error.kind, RuntimeErrorKind::StackOverflow);
+        assert_eq!(error.kind.to_string(), "Stack overflow.");
+        assert_eq!(error.trace.len(), MAX_CALL_DEPTH);
+
+        // the calls that did finish don't count against the ones after them
+        let source = "fun f(n) { if (n > 0) return f(n - 1); return 0; }
+var result = 0;
+for (var i = 0; i < 10; i = i + 1) result = result + f(200);";
+        assert_eq!(run(source).unwrap(), Value::Number(0.0));
+    }
 }
@@ -1,18 +1,21 @@
+use std::borrow::Cow;
+
+use crate::span::Span;
 use crate::Token;
 
 #[derive(Debug, Clone)]
-pub enum ExprLiteral {
-    Number(String),
-    String(String),
+pub enum ExprLiteral<'src> {
+    Number(f64),
+    String(Cow<'src, str>),
     Bool(bool),
-    Identifier(String),
+    Identifier(&'src str),
     Nil,
 }
 
-impl std::fmt::Display for ExprLiteral {
+impl std::fmt::Display for ExprLiteral<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ExprLiteral::Number(num) => write!(f, "{}", num.parse::<String>().unwrap()),
+            ExprLiteral::Number(num) => crate::write_number(f, *num),
             ExprLiteral::String(str) => {
                 write!(f, "{str}")
             }
@@ -24,49 +27,74 @@ impl std::fmt::Display for ExprLiteral {
 }
 
 #[derive(Debug, Clone)]
-pub enum Expr {
-    Literal(ExprLiteral),
-    Grouping(Box<Expr>),
-    Unary(Token, Box<Expr>),
-    Binary(Token, Box<Expr>, Box<Expr>),
-    Assignment(Box<Expr>, Box<Expr>),
+pub struct Expr<'src> {
+    pub kind: ExprKind<'src>,
+    pub span: Span,
 }
 
-impl std::fmt::Display for Expr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Expr::Literal(literal) => write!(f, "{literal}"),
-            Expr::Grouping(expr) => write!(f, "(group {})", *expr),
-            Expr::Unary(operator, expr) => write!(f, "({} {})", operator._string, *expr),
-            Expr::Binary(operator, left, right) => {
-                write!(f, "({} {} {})", operator._string, *left, *right)
-            }
-            Expr::Assignment(name, expr) => write!(f, "({}) = ({})", name, expr),
-        }
+impl<'src> Expr<'src> {
+    pub fn new(kind: ExprKind<'src>, span: Span) -> Self {
+        Self { kind, span }
     }
 }
-#[derive(Debug)]
-pub struct ExprError {
-    pub msg: String,
-    pub code: i32,
-}
 
-impl ExprError {
-    pub fn new(msg: String, code: i32) -> Self {
-        let msg = "Error: ".to_owned() + msg.as_ref();
-        Self { msg, code }
-    }
+#[derive(Debug, Clone)]
+pub enum ExprKind<'src> {
+    Literal(ExprLiteral<'src>),
+    Grouping(Box<Expr<'src>>),
+    Unary(Token<'src>, Box<Expr<'src>>),
+    Binary(Token<'src>, Box<Expr<'src>>, Box<Expr<'src>>),
+    Assignment(Box<Expr<'src>>, Box<Expr<'src>>),
+    Logical(Token<'src>, Box<Expr<'src>>, Box<Expr<'src>>),
+    Call(Box<Expr<'src>>, Token<'src>, Vec<Expr<'src>>),
+    Get(Box<Expr<'src>>, Token<'src>),
+    Set(Box<Expr<'src>>, Token<'src>, Box<Expr<'src>>),
+    This(Token<'src>),
+    Super(Token<'src>, Token<'src>),
+    /// A string with `${...}` in it: the literal text and the embedded
+    /// expressions in source order, stringified and joined when evaluated.
+    Interpolation(Vec<Expr<'src>>),
+}
 
-    pub fn MissingToken(code: i32) -> Self {
-        Self {
-            msg: "".to_string(),
-            code,
-        }
+impl std::fmt::Display for Expr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
     }
 }
 
-impl std::fmt::Display for ExprError {
+impl std::fmt::Display for ExprKind<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.msg)
+        match self {
+            ExprKind::Literal(literal) => write!(f, "{literal}"),
+            ExprKind::Grouping(expr) => write!(f, "(group {})", *expr),
+            ExprKind::Unary(operator, expr) => write!(f, "({} {})", operator._string, *expr),
+            ExprKind::Binary(operator, left, right) => {
+                write!(f, "({} {} {})", operator._string, *left, *right)
+            }
+            ExprKind::Assignment(name, expr) => write!(f, "({}) = ({})", name, expr),
+            ExprKind::Logical(operator, left, right) => {
+                write!(f, "({} {} {})", operator._string, *left, *right)
+            }
+            ExprKind::Call(callee, _, arguments) => {
+                write!(f, "(call {}", callee)?;
+                for argument in arguments {
+                    write!(f, " {}", argument)?;
+                }
+                write!(f, ")")
+            }
+            ExprKind::Get(object, name) => write!(f, "(. {} {})", object, name._string),
+            ExprKind::Set(object, name, value) => {
+                write!(f, "(= (. {} {}) {})", object, name._string, value)
+            }
+            ExprKind::This(_) => write!(f, "this"),
+            ExprKind::Super(_, method) => write!(f, "(super {})", method._string),
+            ExprKind::Interpolation(parts) => {
+                write!(f, "(interpolate")?;
+                for part in parts {
+                    write!(f, " {}", part)?;
+                }
+                write!(f, ")")
+            }
+        }
     }
 }
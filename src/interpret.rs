@@ -1,66 +1,177 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
 use crate::{
-    eval::{Evaluator, RuntimeError},
-    expr::{Expr, ExprLiteral},
-    smnt::Statment,
+    environment::{Binding, Environment},
+    eval::{Class, Evaluator, Function, RuntimeError, RuntimeErrorKind, Value},
+    smnt::{Statment, StatmentKind},
 };
 
-#[derive(Clone)]
-pub struct Interpreter {
-    evaluater: Evaluator,
-    line: i32,
+pub struct Interpreter<'src> {
+    evaluater: Evaluator<'src>,
+}
+
+/// How a statement finished: either fell through to the next one, or hit a
+/// `return` that has to unwind out to the enclosing call.
+pub enum Flow<'src> {
+    Normal,
+    Return(Value<'src>),
 }
 
-impl Interpreter {
+impl<'src> Interpreter<'src> {
     pub fn new() -> Self {
         Self {
             evaluater: Evaluator::new(),
-            line: 0,
         }
     }
 
-    pub fn interpret(&mut self, statements: Vec<Statment>) -> Result<(), RuntimeError> {
-        for statments in statements {
-            match statments {
-                Statment::PrintStmt(expr) => {
-                    let value = self.evaluater.evaluate(&expr);
-                    match value {
-                        Ok(val) => {
-                            println!("{val}")
-                        }
-                        Err(e) => {
-                            return Err(e);
-                        }
+    /// Hands over the local bindings worked out by the resolver.
+    pub fn resolve(&mut self, locals: HashMap<usize, Binding>) {
+        self.evaluater.locals.extend(locals);
+    }
+
+    pub fn interpret(&mut self, statements: Vec<Statment<'src>>) -> Result<(), RuntimeError> {
+        for statment in &statements {
+            self.evaluater.execute(statment)?;
+        }
+        Ok(())
+    }
+}
+
+// statements live here rather than in eval.rs, but function calls are
+// expressions that run statements so they need to share the one Evaluator
+impl<'src> Evaluator<'src> {
+    pub fn execute(&mut self, statment: &Statment<'src>) -> Result<Flow<'src>, RuntimeError> {
+        match &statment.kind {
+            StatmentKind::PrintStmt(expr) => {
+                let value = self.evaluate(expr);
+                match value {
+                    Ok(val) => {
+                        println!("{val}")
+                    }
+                    Err(e) => {
+                        return Err(e);
                     }
                 }
-                Statment::VarDec(Name, value) => {
-                    let variable_name = if let Expr::Literal(ExprLiteral::Identifier(str)) = Name {
-                        str.clone().to_string()
-                    } else {
-                        return Err(RuntimeError::new(
-                            "failed to interpret variable name".to_string(),
-                            self.evaluater.line,
-                        ));
-                    };
-                    let val = self.evaluater.evaluate(&value)?;
-                    self.evaluater.symbols.insert(variable_name, val);
+            }
+            StatmentKind::VarDec(name, value) => {
+                let val = self.evaluate(value)?;
+                self.define(name, val);
+            }
+            StatmentKind::BlockStatment(tehes) => {
+                let environment = Environment::with_enclosing(self.environment.clone());
+                return self.execute_block(tehes, environment);
+            }
+            StatmentKind::If(condition, then_branch, else_branch) => {
+                if self.evaluate(condition)?.is_truthy() {
+                    return self.execute(then_branch);
+                } else if let Some(else_branch) = else_branch {
+                    return self.execute(else_branch);
                 }
-                Statment::BlockStatment(tehes) => {
-                    let mut new_enviroment = self.clone();
-                    new_enviroment.interpret(tehes.as_ref().clone())?;
+            }
+            StatmentKind::While(condition, body) => {
+                while self.evaluate(condition)?.is_truthy() {
+                    if let Flow::Return(value) = self.execute(body)? {
+                        return Ok(Flow::Return(value));
+                    }
                 }
-                Statment::ExprStmt(expr) => {
-                    let value = self.evaluater.evaluate(&expr);
-
-                    match value {
-                        Ok(val) => {}
-                        Err(e) => {
-                            return Err(e);
+            }
+            StatmentKind::Function(declaration) => {
+                let function = Value::Function(Rc::new(Function {
+                    declaration: declaration.clone(),
+                    closure: self.environment.clone(),
+                    is_initializer: false,
+                }));
+                self.define(&declaration.name, function);
+            }
+            StatmentKind::Class(declaration) => {
+                let superclass = match &declaration.superclass {
+                    Some(expr) => match self.evaluate(expr)? {
+                        Value::Class(class) => Some(class),
+                        other => {
+                            return Err(RuntimeError::new(
+                                RuntimeErrorKind::SuperclassNotClass(other.type_name()),
+                                expr.span,
+                            ))
                         }
+                    },
+                    None => None,
+                };
+
+                // methods of a subclass close over an extra scope holding `super`
+                let closure = match &superclass {
+                    Some(superclass) => {
+                        let mut environment = Environment::with_enclosing(self.environment.clone());
+                        environment.define_at(0, Value::Class(superclass.clone()));
+                        environment.into_ref()
+                    }
+                    None => self.environment.clone(),
+                };
+
+                let methods = declaration
+                    .methods
+                    .iter()
+                    .map(|method| {
+                        let name = method.name._string.to_string();
+                        let function = Function {
+                            declaration: method.clone(),
+                            closure: closure.clone(),
+                            is_initializer: name == "init",
+                        };
+                        (name, Rc::new(function))
+                    })
+                    .collect();
+                let class = Value::Class(Rc::new(Class {
+                    name: declaration.name._string.to_string(),
+                    superclass,
+                    methods,
+                }));
+                self.define(&declaration.name, class);
+            }
+            StatmentKind::Return(_, value) => {
+                let value = match value {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => Value::Nil,
+                };
+                return Ok(Flow::Return(value));
+            }
+            StatmentKind::ExprStmt(expr) => {
+                let value = self.evaluate(expr);
+
+                match value {
+                    Ok(val) => {}
+                    Err(e) => {
+                        return Err(e);
                     }
                 }
             }
-            self.evaluater.line += 1;
         }
-        Ok(())
+        Ok(Flow::Normal)
+    }
+
+    /// Runs statements inside `environment`, switching back to the current
+    /// scope afterwards even if one of them fails.
+    pub fn execute_block(
+        &mut self,
+        statments: &[Statment<'src>],
+        environment: Environment<'src>,
+    ) -> Result<Flow<'src>, RuntimeError> {
+        let previous = std::mem::replace(&mut self.environment, environment.into_ref());
+        let result = self.execute_all(statments);
+        self.environment = previous;
+        result
+    }
+
+    /// Runs statements in order, stopping early if one of them returns.
+    pub fn execute_all(
+        &mut self,
+        statments: &[Statment<'src>],
+    ) -> Result<Flow<'src>, RuntimeError> {
+        for statment in statments {
+            if let Flow::Return(value) = self.execute(statment)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Normal)
     }
 }
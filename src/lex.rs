@@ -0,0 +1,708 @@
+use std::borrow::Cow;
+
+use crate::diagnostic::{Diagnostic, Phase};
+use crate::span::Span;
+use crate::unicode;
+use crate::{Literal, Token, TokenType};
+
+// a letter or `_`, then also marks, digits and connectors, as in UAX #31
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || (c.is_alphabetic() && !unicode::is_mark(c))
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    is_identifier_start(c)
+        || unicode::is_mark(c)
+        || unicode::is_digit_or_connector(c)
+        // the joiners UAX #31 allows inside a name
+        || matches!(c, '\u{200C}' | '\u{200D}')
+}
+
+fn keyword(word: &str) -> Option<TokenType> {
+    let keyword = match word {
+        "and" => TokenType::And,
+        "class" => TokenType::Class,
+        "else" => TokenType::Else,
+        "false" => TokenType::False,
+        "for" => TokenType::For,
+        "fun" => TokenType::Fun,
+        "if" => TokenType::If,
+        "nil" => TokenType::Nil,
+        "or" => TokenType::or,
+        "print" => TokenType::Print,
+        "return" => TokenType::Return,
+        "super" => TokenType::Super,
+        "this" => TokenType::This,
+        "true" => TokenType::True,
+        "var" => TokenType::Var,
+        "while" => TokenType::While,
+        _ => return None,
+    };
+    Some(keyword)
+}
+
+/// Scans source text into tokens on demand. Lexemes are slices of the
+/// source rather than copies, and the scan works on bytes, only decoding a
+/// whole character where non-ASCII text is allowed.
+///
+/// A bad character or literal doesn't stop the scan: it's recorded in
+/// `errors` and lexing carries on, so every one gets reported. The last
+/// token is always EOF.
+///
+/// An interpolated string like `"a ${b} c"` comes out as an `Interpolation`
+/// token for `"a ${`, the tokens of `b`, then a `String` token for `} c"`.
+pub struct Lexer<'src> {
+    source: &'src str,
+    offset: usize,
+    line: u32,
+    column: u32,
+    start: Span,
+    finished: bool,
+    /// One entry per `${` still waiting for its `}`, innermost last: where it
+    /// opened, and how many `{` inside it are still unclosed.
+    interpolations: Vec<(Span, u32)>,
+    pub errors: Vec<Diagnostic>,
+}
+
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Token<'src>;
+
+    fn next(&mut self) -> Option<Token<'src>> {
+        loop {
+            self.start = Span::new(self.line, self.column, self.offset, self.offset);
+            let Some(char) = self.bump() else {
+                if self.finished {
+                    return None;
+                }
+                self.finished = true;
+                for (span, _) in std::mem::take(&mut self.interpolations) {
+                    let msg = "Error: Unterminated string interpolation.".to_string();
+                    self.error(span, msg);
+                }
+                return Some(self.token(TokenType::EOF, None));
+            };
+            let _type = match char {
+                ' ' | '\r' | '\t' | '\n' => continue,
+                '(' => TokenType::LeftParen,
+                ')' => TokenType::RightParen,
+                '{' => {
+                    if let Some((_, depth)) = self.interpolations.last_mut() {
+                        *depth += 1;
+                    }
+                    TokenType::LeftBrace
+                }
+                // the `}` closing an interpolation picks the string back up
+                '}' if self
+                    .interpolations
+                    .last()
+                    .is_some_and(|&(_, depth)| depth == 0) =>
+                {
+                    self.interpolations.pop();
+                    match self.string() {
+                        Some(string) => return Some(string),
+                        None => continue,
+                    }
+                }
+                '}' => {
+                    if let Some((_, depth)) = self.interpolations.last_mut() {
+                        *depth -= 1;
+                    }
+                    TokenType::RightBrace
+                }
+                ',' => TokenType::Comma,
+                '.' => TokenType::Dot,
+                '-' => TokenType::Minus,
+                '+' => TokenType::Plus,
+                '*' => TokenType::Star,
+                ';' => TokenType::SemiColon,
+                '=' if self.eat(b'=') => TokenType::EqualEqual,
+                '=' => TokenType::EQUAL,
+                '!' if self.eat(b'=') => TokenType::BangEqual,
+                '!' => TokenType::Bang,
+                '<' if self.eat(b'=') => TokenType::LessThanEquals,
+                '<' => TokenType::LessThan,
+                '>' if self.eat(b'=') => TokenType::GreaterThanEquals,
+                '>' => TokenType::GreaterThan,
+                '/' if self.eat(b'/') => {
+                    while self.peek().is_some_and(|byte| byte != b'\n') {
+                        self.offset += 1;
+                    }
+                    continue;
+                }
+                '/' if self.eat(b'*') => {
+                    self.block_comment();
+                    continue;
+                }
+                '/' => TokenType::Slash,
+                '"' => match self.string() {
+                    Some(string) => return Some(string),
+                    None => continue,
+                },
+                char if is_identifier_start(char) => return Some(self.identifier()),
+                char if char.is_ascii_digit() => match self.number(char) {
+                    Ok(number) => return Some(number),
+                    Err(msg) => {
+                        self.error(self.lexeme_span(), msg);
+                        continue;
+                    }
+                },
+                char => {
+                    let msg = format!("Error: Unexpected character: {}", char);
+                    self.error(self.lexeme_span(), msg);
+                    continue;
+                }
+            };
+            return Some(self.token(_type, None));
+        }
+    }
+}
+
+impl<'src> Lexer<'src> {
+    pub fn new(source: &'src str) -> Self {
+        Self {
+            source,
+            offset: 0,
+            line: 1,
+            column: 1,
+            start: Span::default(),
+            finished: false,
+            interpolations: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.source.as_bytes().get(self.offset).copied()
+    }
+
+    fn peek_next(&self) -> Option<u8> {
+        self.source.as_bytes().get(self.offset + 1).copied()
+    }
+
+    // consumes one character, keeping the line, column and byte offset in step with it
+    fn bump(&mut self) -> Option<char> {
+        let byte = self.peek()?;
+        let char = if byte.is_ascii() {
+            byte as char
+        } else {
+            self.source[self.offset..].chars().next()?
+        };
+        self.offset += char.len_utf8();
+        if char == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(char)
+    }
+
+    // consumes the next byte only if it's `expected`, which must be ASCII
+    fn eat(&mut self, expected: u8) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn lexeme(&self) -> &'src str {
+        &self.source[self.start.start..self.offset]
+    }
+
+    fn lexeme_span(&self) -> Span {
+        Span {
+            end: self.offset,
+            ..self.start
+        }
+    }
+
+    // builds a token spanning from where the current lexeme started to the current offset
+    fn token(&self, _type: TokenType, _value: Option<Literal<'src>>) -> Token<'src> {
+        let mut token = Token::newToken(_type, self.lexeme(), _value);
+        token._span = self.lexeme_span();
+        token
+    }
+
+    // lexing carries on after an error so every bad character gets reported
+    fn error(&mut self, span: Span, msg: String) {
+        self.errors.push(Diagnostic::error(Phase::Lex, span, msg));
+    }
+
+    // skips a block comment whose `/*` has been consumed; they nest, so this
+    // counts how many are still open
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            let Some(char) = self.bump() else {
+                let span = Span {
+                    end: self.start.start + 2,
+                    ..self.start
+                };
+                self.error(span, "Error: Unterminated block comment.".to_string());
+                return;
+            };
+            match char {
+                '*' if self.eat(b'/') => depth -= 1,
+                '/' if self.eat(b'*') => depth += 1,
+                _ => {}
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> Token<'src> {
+        while let Some(byte) = self.peek() {
+            if byte.is_ascii() {
+                if !byte.is_ascii_alphanumeric() && byte != b'_' {
+                    break;
+                }
+                self.offset += 1;
+                self.column += 1;
+            } else {
+                match self.source[self.offset..].chars().next() {
+                    Some(char) if is_identifier_continue(char) => {
+                        self.bump();
+                    }
+                    _ => break,
+                }
+            }
+        }
+        let _type = keyword(self.lexeme()).unwrap_or(TokenType::Identifer);
+        self.token(_type, None)
+    }
+
+    // scans a string literal whose opening quote has been consumed, or the
+    // rest of one after an interpolation's closing `}`, up to the closing
+    // quote or the next `${`. The lexeme is the source text as written; the
+    // value has escapes decoded. Returns nothing if the literal was bad,
+    // having reported why.
+    fn string(&mut self) -> Option<Token<'src>> {
+        // only allocated once an escape means the value differs from the
+        // source text; until then it's borrowed from `content` on
+        let mut value: Option<String> = None;
+        let content = self.offset;
+        // start of the text since the last escape, copied over in one go
+        let mut plain = self.offset;
+        let mut valid = true;
+        loop {
+            match self.peek() {
+                None => {
+                    // reported where the input ran out, like jlox
+                    let span = Span::new(self.line, self.column, self.offset, self.offset);
+                    self.error(span, "Error: Unterminated string.".to_string());
+                    return None;
+                }
+                Some(b'"') => {
+                    let value = self.text(value, content, plain);
+                    self.bump();
+                    return valid
+                        .then(|| self.token(TokenType::String, Some(Literal::String(value))));
+                }
+                Some(b'$') if self.peek_next() == Some(b'{') => {
+                    let value = self.text(value, content, plain);
+                    let open = Span::new(self.line, self.column, self.offset, self.offset + 2);
+                    self.bump();
+                    self.bump();
+                    self.interpolations.push((open, 0));
+                    return valid.then(|| {
+                        self.token(TokenType::Interpolation, Some(Literal::String(value)))
+                    });
+                }
+                // a backslash at the very end is just an unterminated string
+                Some(b'\\') if self.peek_next().is_none() => {
+                    self.bump();
+                }
+                Some(b'\\') => {
+                    let decoded = value.get_or_insert_with(String::new);
+                    decoded.push_str(&self.source[plain..self.offset]);
+                    let mut span = Span::new(self.line, self.column, self.offset, 0);
+                    self.bump();
+                    match self.escape() {
+                        Ok(escaped) => decoded.push(escaped),
+                        Err(msg) => {
+                            valid = false;
+                            span.end = self.offset;
+                            self.error(span, msg);
+                        }
+                    }
+                    plain = self.offset;
+                }
+                Some(_) => {
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    // the value of string text from `content` up to here: borrowed from the
+    // source if it had no escapes, otherwise what was decoded plus the text
+    // since the last escape
+    fn text(&self, decoded: Option<String>, content: usize, plain: usize) -> Cow<'src, str> {
+        match decoded {
+            Some(mut decoded) => {
+                decoded.push_str(&self.source[plain..self.offset]);
+                Cow::Owned(decoded)
+            }
+            None => Cow::Borrowed(&self.source[content..self.offset]),
+        }
+    }
+
+    // decodes the escape after a backslash in a string literal
+    fn escape(&mut self) -> Result<char, String> {
+        let Some(char) = self.bump() else {
+            return Err("Error: Unterminated string.".to_string());
+        };
+        match char {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' => Ok('\\'),
+            '"' => Ok('"'),
+            '$' => Ok('$'),
+            'u' => {
+                let invalid = || "Error: Invalid unicode escape sequence.".to_string();
+                if !self.eat(b'{') {
+                    return Err(invalid());
+                }
+                let digits = self.offset;
+                while self.peek().is_some_and(|byte| byte.is_ascii_hexdigit()) {
+                    self.bump();
+                }
+                let digits = &self.source[digits..self.offset];
+                if !self.eat(b'}') || digits.is_empty() || digits.len() > 6 {
+                    return Err(invalid());
+                }
+                u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(invalid)
+            }
+            '\n' => Err("Error: Invalid escape sequence: \\ followed by a newline.".to_string()),
+            other => Err(format!("Error: Invalid escape sequence: \\{}", other)),
+        }
+    }
+
+    // scans a number literal whose first digit has already been consumed:
+    // decimal with an optional fraction and exponent, or 0x/0b/0o integers,
+    // any of them with `_` between digits
+    fn number(&mut self, first: char) -> Result<Token<'src>, String> {
+        let radix = match (first, self.peek()) {
+            ('0', Some(b'x' | b'X')) => Some((16, "hexadecimal")),
+            ('0', Some(b'b' | b'B')) => Some((2, "binary")),
+            ('0', Some(b'o' | b'O')) => Some((8, "octal")),
+            _ => None,
+        };
+
+        if let Some((radix, name)) = radix {
+            self.bump();
+            // take the whole word so `0b102` is one bad literal rather than `0b10` and `2`
+            while self
+                .peek()
+                .is_some_and(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+            {
+                self.bump();
+            }
+            let lexeme = self.lexeme();
+            let digits = &lexeme[2..];
+            if digits.is_empty() {
+                return Err(format!("Error: Missing digits after '{}'.", lexeme));
+            }
+            if let Some(bad) = digits.chars().find(|&c| c != '_' && !c.is_digit(radix)) {
+                return Err(format!(
+                    "Error: Invalid digit '{}' in {} literal '{}'.",
+                    bad, name, lexeme
+                ));
+            }
+            Self::check_separators(lexeme, radix)?;
+            let value = u64::from_str_radix(&digits.replace('_', ""), radix)
+                .map_err(|_| format!("Error: Number literal '{}' is too large.", lexeme))?;
+            return Ok(self.token(TokenType::Number, Some(Literal::Number(value as f64))));
+        }
+
+        self.decimal_digits();
+        // `1.` is the number 1 followed by a dot, not a fraction
+        if self.peek() == Some(b'.') && self.peek_next().is_some_and(|byte| byte.is_ascii_digit()) {
+            self.bump();
+            self.decimal_digits();
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.bump();
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.bump();
+            }
+            if !self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+                return Err(format!(
+                    "Error: Missing exponent digits in '{}'.",
+                    self.lexeme()
+                ));
+            }
+            self.decimal_digits();
+        }
+        let lexeme = self.lexeme();
+        Self::check_separators(lexeme, 10)?;
+
+        // only digits, one dot and a well-formed exponent got in, so this always parses
+        let value = if lexeme.contains('_') {
+            lexeme.replace('_', "").parse().unwrap()
+        } else {
+            lexeme.parse().unwrap()
+        };
+        Ok(self.token(TokenType::Number, Some(Literal::Number(value))))
+    }
+
+    fn decimal_digits(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|byte| byte.is_ascii_digit() || byte == b'_')
+        {
+            self.bump();
+        }
+    }
+
+    // a `_` is only allowed with a digit on both sides of it
+    fn check_separators(lexeme: &str, radix: u32) -> Result<(), String> {
+        let bytes = lexeme.as_bytes();
+        let is_digit = |i: Option<usize>| {
+            i.and_then(|i| bytes.get(i))
+                .is_some_and(|&byte| (byte as char).is_digit(radix))
+        };
+        for (i, &byte) in bytes.iter().enumerate() {
+            if byte == b'_' && !(is_digit(i.checked_sub(1)) && is_digit(Some(i + 1))) {
+                return Err(format!("Error: Invalid digit separator in '{}'.", lexeme));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    // every token but the trailing EOF, as tokenize prints it, and every
+    // error, as the plain format prints it
+    fn lex(source: &str) -> (Vec<String>, Vec<String>) {
+        let mut lexer = Lexer::new(source);
+        let mut tokens: Vec<String> = lexer.by_ref().map(|token| token.to_string()).collect();
+        assert_eq!(tokens.pop().as_deref(), Some("EOF  null"));
+        let errors = lexer.errors.iter().map(|e| e.to_string()).collect();
+        (tokens, errors)
+    }
+
+    #[test]
+    fn string_escapes_are_decoded() {
+        let (tokens, errors) = lex(r#""a\nb\t\"q\\ \u{e9}\u{1F600}\0""#);
+        assert!(errors.is_empty());
+        assert_eq!(
+            tokens,
+            ["STRING \"a\\nb\\t\\\"q\\\\ \\u{e9}\\u{1F600}\\0\" a\nb\t\"q\\ \u{e9}\u{1F600}\0"]
+        );
+
+        // a newline written into a string still moves the line on
+        let tokens: Vec<_> = Lexer::new("\"x\ny\" z").collect();
+        assert_eq!(tokens[0]._value, Some(Literal::String("x\ny".into())));
+        assert_eq!(tokens[1]._span.line, 2);
+    }
+
+    #[test]
+    fn bad_escapes_are_reported() {
+        let cases = [
+            (r#""\q""#, "[line 1] Error: Invalid escape sequence: \\q"),
+            (
+                r#""\u{D800}""#,
+                "[line 1] Error: Invalid unicode escape sequence.",
+            ),
+            (
+                r#""\u{110000}""#,
+                "[line 1] Error: Invalid unicode escape sequence.",
+            ),
+            (
+                r#""\u{}""#,
+                "[line 1] Error: Invalid unicode escape sequence.",
+            ),
+            (
+                r#""\u00e9""#,
+                "[line 1] Error: Invalid unicode escape sequence.",
+            ),
+            // a backslash just before the input ends escapes nothing
+            ("\"ab\\", "[line 1] Error: Unterminated string."),
+            ("\"a\nb\\", "[line 2] Error: Unterminated string."),
+        ];
+        for (source, expected) in cases {
+            let (tokens, errors) = lex(source);
+            assert!(tokens.is_empty(), "{source}");
+            assert_eq!(errors, [expected], "{source}");
+        }
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        let (tokens, errors) = lex("a /* one /* two */ still one */ b /**/ c");
+        assert!(errors.is_empty());
+        assert_eq!(
+            tokens,
+            [
+                "IDENTIFIER a null",
+                "IDENTIFIER b null",
+                "IDENTIFIER c null"
+            ]
+        );
+
+        // the comment closes at the first `*/`, so the second one is code
+        let (tokens, _) = lex("/* */ */");
+        assert_eq!(tokens, ["STAR * null", "SLASH / null"]);
+
+        let tokens: Vec<_> = Lexer::new("/* a\n/* b */\n*/ x").collect();
+        assert_eq!(tokens[0]._span.line, 3);
+    }
+
+    #[test]
+    fn unterminated_block_comments_are_reported_where_they_open() {
+        let cases = [
+            (
+                "/* never closed",
+                "[line 1] Error: Unterminated block comment.",
+            ),
+            (
+                "x\n/* outer /* inner */\n\n",
+                "[line 2] Error: Unterminated block comment.",
+            ),
+        ];
+        for (source, expected) in cases {
+            let (_, errors) = lex(source);
+            assert_eq!(errors, [expected], "{source}");
+        }
+    }
+
+    #[test]
+    fn number_literals_in_every_radix() {
+        let (tokens, errors) = lex("0x1F 0XfF 0b1010 0o17 1e-9 2.5E+3 1_000 1.e5");
+        assert_eq!(
+            tokens,
+            [
+                "NUMBER 0x1F 31.0",
+                "NUMBER 0XfF 255.0",
+                "NUMBER 0b1010 10.0",
+                "NUMBER 0o17 15.0",
+                "NUMBER 1e-9 0.000000001",
+                "NUMBER 2.5E+3 2500.0",
+                "NUMBER 1_000 1000.0",
+                // `1.` is a number and a dot, so `e5` is a name
+                "NUMBER 1 1.0",
+                "DOT . null",
+                "IDENTIFIER e5 null",
+            ]
+        );
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn malformed_number_literals_are_reported() {
+        let cases = [
+            ("0x", "Missing digits after '0x'."),
+            ("0b", "Missing digits after '0b'."),
+            ("1e", "Missing exponent digits in '1e'."),
+            ("1e+", "Missing exponent digits in '1e+'."),
+            ("1__0", "Invalid digit separator in '1__0'."),
+            ("1_", "Invalid digit separator in '1_'."),
+            ("0b102", "Invalid digit '2' in binary literal '0b102'."),
+            ("0o8", "Invalid digit '8' in octal literal '0o8'."),
+            ("0xfg", "Invalid digit 'g' in hexadecimal literal '0xfg'."),
+            (
+                "0x1_0000_0000_0000_0000",
+                "Number literal '0x1_0000_0000_0000_0000' is too large.",
+            ),
+        ];
+        for (source, expected) in cases {
+            let (tokens, errors) = lex(source);
+            assert!(tokens.is_empty(), "{source}");
+            assert_eq!(errors, [format!("[line 1] Error: {expected}")], "{source}");
+        }
+    }
+
+    #[test]
+    fn identifiers_from_any_script() {
+        let (tokens, errors) = lex("café नमस्ते x٣ e\u{301} a\u{203F}b _1 Ⅻ");
+        assert!(errors.is_empty());
+        assert_eq!(
+            tokens,
+            [
+                "IDENTIFIER café null",
+                "IDENTIFIER नमस्ते null",
+                "IDENTIFIER x٣ null",
+                "IDENTIFIER e\u{301} null",
+                "IDENTIFIER a\u{203F}b null",
+                "IDENTIFIER _1 null",
+                "IDENTIFIER Ⅻ null",
+            ]
+        );
+    }
+
+    #[test]
+    fn symbols_and_other_numbers_are_not_identifiers() {
+        let cases = [
+            ("😀", "😀"),
+            ("a😀", "😀"),
+            ("x²", "²"),
+            ("½", "½"),
+            // a combining mark can't start a name
+            ("\u{94D}a", "\u{94D}"),
+        ];
+        for (source, bad) in cases {
+            let (_, errors) = lex(source);
+            let expected = format!("[line 1] Error: Unexpected character: {bad}");
+            assert_eq!(errors, [expected], "{source}");
+        }
+    }
+
+    #[test]
+    fn number_tokens_print_in_full_with_a_fraction() {
+        let cases = [
+            ("42", "NUMBER 42 42.0"),
+            ("1.5", "NUMBER 1.5 1.5"),
+            (
+                "10000000000000000",
+                "NUMBER 10000000000000000 10000000000000000.0",
+            ),
+            (
+                "1234567890123456789",
+                "NUMBER 1234567890123456789 1234567890123456800.0",
+            ),
+            ("0.0000001", "NUMBER 0.0000001 0.0000001"),
+            ("1e400", "NUMBER 1e400 Infinity"),
+        ];
+        for (source, expected) in cases {
+            let token = Lexer::new(source).next().unwrap();
+            assert_eq!(token.to_string(), expected);
+        }
+    }
+
+    // a quick sanity check on speed, not a benchmark: run it with
+    // `cargo test --release -- --ignored --nocapture lexer_throughput`
+    #[test]
+    #[ignore]
+    fn lexer_throughput() {
+        let unit = "var count_1 = 0x1F + 12.5e3; // comment\n\
+                    fun add(a, b) { return a + b * \"str\\tx\"; }\n\
+                    /* block */ while (count_1 < 10) { count_1 = count_1 + 1; }\n";
+        let source = unit.repeat(40_000);
+
+        let start = Instant::now();
+        let mut lexer = Lexer::new(&source);
+        let tokens: Vec<Token> = lexer.by_ref().collect();
+        let elapsed = start.elapsed();
+
+        assert!(lexer.errors.is_empty());
+        assert_eq!(tokens.len(), 37 * 40_000 + 1);
+        eprintln!(
+            "lexed {} bytes into {} tokens in {:?} ({:.0} MB/s)",
+            source.len(),
+            tokens.len(),
+            elapsed,
+            source.len() as f64 / 1e6 / elapsed.as_secs_f64()
+        );
+    }
+}
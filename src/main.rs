@@ -1,368 +1,90 @@
 use core::fmt;
-use std::char;
+use std::borrow::Cow;
 use std::env;
 use std::fs;
-use std::io::stderr;
 use std::io::{self, Write};
-use std::iter::Peekable;
 use std::process::exit;
 
+use diagnostic::{Diagnostic, Reporter, Style};
 use eval::Evaluator;
 use interpret::Interpreter;
+use lex::Lexer;
+use span::Span;
 
+mod diagnostic;
+mod environment;
 mod eval;
 mod expr;
 mod interpret;
+mod lex;
 mod parse;
+mod resolve;
 mod smnt;
+mod span;
+mod unicode;
 
-struct Lexer {
-    line: i32,
-    tokens: Vec<Token>,
+/// A token as the lexer produced it. The lexeme borrows from the source
+/// text, so tokens and everything built from them live as long as it does.
+#[derive(Clone, Debug)]
+pub struct Token<'src> {
+    _type: TokenType,
+    _string: &'src str,
+    _value: Option<Literal<'src>>,
+    _span: Span,
 }
 
-impl Lexer {
-    fn new() -> Self {
-        let mut line = 1;
-        Self {
-            line,
-            tokens: Vec::new(),
+/// Value of a literal token, worked out once by the lexer. A string borrows
+/// its text from the source unless escapes made it differ.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal<'src> {
+    String(Cow<'src, str>),
+    Number(f64),
+}
+
+impl fmt::Display for Literal<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::String(s) => write!(f, "{s}"),
+            Literal::Number(n) => write_number(f, *n),
         }
     }
+}
 
-    fn tokenize<I>(&mut self, characters: &mut Peekable<I>) -> i32
-    where
-        I: Iterator<Item = char> + Clone,
-    {
-        let mut exitcode = 0;
-        let mut tokens = Vec::new();
-
-        while let Some(char) = characters.next() {
-            match char {
-                '\n' => {
-                    self.line += 1;
-                }
-                ' ' | '\r' | '\t' => {}
-                '(' => tokens.push(Token::newToken(
-                    TokenType::LeftParen,
-                    char.to_string(),
-                    None,
-                )),
-                ')' => tokens.push(Token::newToken(
-                    TokenType::RightParen,
-                    char.to_string(),
-                    None,
-                )),
-                '{' => tokens.push(Token::newToken(
-                    TokenType::LeftBrace,
-                    char.to_string(),
-                    None,
-                )),
-                '}' => tokens.push(Token::newToken(
-                    TokenType::RightBrace,
-                    char.to_string(),
-                    None,
-                )),
-                ',' => tokens.push(Token::newToken(TokenType::Comma, char.to_string(), None)),
-                '.' => tokens.push(Token::newToken(TokenType::Dot, char.to_string(), None)),
-                '-' => tokens.push(Token::newToken(TokenType::Minus, char.to_string(), None)),
-                '+' => tokens.push(Token::newToken(TokenType::Plus, char.to_string(), None)),
-                '*' => tokens.push(Token::newToken(TokenType::Star, char.to_string(), None)),
-                ';' => tokens.push(Token::newToken(
-                    TokenType::SemiColon,
-                    char.to_string(),
-                    None,
-                )),
-                '=' => {
-                    let mut peeker = characters.clone().peekable();
-                    if peeker.next() == Some('=') {
-                        tokens.push(Token::newToken(
-                            TokenType::EqualEqual,
-                            "==".to_string(),
-                            None,
-                        ));
-                        characters.next();
-                    } else {
-                        tokens.push(Token::newToken(TokenType::EQUAL, char.to_string(), None));
-                    }
-                }
-                '!' => {
-                    let mut peeker = characters.clone().peekable();
-                    if peeker.next() == Some('=') {
-                        tokens.push(Token::newToken(
-                            TokenType::BangEqual,
-                            "!=".to_string(),
-                            None,
-                        ));
-                        characters.next();
-                    } else {
-                        tokens.push(Token::newToken(TokenType::Bang, char.to_string(), None));
-                    }
-                }
-                '<' => {
-                    let mut peeker = characters.clone().peekable();
-                    if peeker.next() == Some('=') {
-                        tokens.push(Token::newToken(
-                            TokenType::LessThanEquals,
-                            "<=".to_string(),
-                            None,
-                        ));
-                        characters.next();
-                    } else {
-                        tokens.push(Token::newToken(TokenType::LessThan, char.to_string(), None));
-                    }
-                }
-                '>' => {
-                    let mut peeker = characters.clone().peekable();
-                    if peeker.next() == Some('=') {
-                        tokens.push(Token::newToken(
-                            TokenType::GreaterThanEquals,
-                            ">=".to_string(),
-                            None,
-                        ));
-                        characters.next();
-                    } else {
-                        tokens.push(Token::newToken(
-                            TokenType::GreaterThan,
-                            char.to_string(),
-                            None,
-                        ));
-                    }
-                }
-                '/' => {
-                    let mut peeker = characters.clone().peekable();
-                    if peeker.peek() == Some(&'/') {
-                        //omgeh we have a comment
-                        while let Some(end) = characters.next() {
-                            if end == '\n' {
-                                self.line += 1;
-                                break;
-                            }
-                        }
-                    } else if peeker.peek() == Some(&'*') {
-                        while let Some(end) = characters.next() {
-                            if end == '*' && characters.next() == Some('/') {
-                                self.line += 1;
-                                break;
-                            }
-                            if end == '\n' {
-                                self.line += 1;
-                            }
-                        }
-                    } else {
-                        tokens.push(Token::newToken(TokenType::Slash, char.to_string(), None))
-                    }
-                }
-                '"' => {
-                    let mut error = true;
-                    let mut value = Vec::new();
-                    while let Some(char) = characters.next() {
-                        match char {
-                            '\\' => {
-                                if characters.peek().unwrap() == &'n' {
-                                    characters.next();
-                                    value.push('\n');
-                                    self.line += 1;
-                                } else {
-                                    value.push('\\');
-                                }
-                            }
-                            '"' => {
-                                error = false;
-                                let inner = value.iter().collect::<String>();
-
-                                tokens.push(Token::newToken(
-                                    TokenType::String,
-                                    format!("\"{}\"", inner),
-                                    Some(inner),
-                                ));
-                                break;
-                            }
-                            _ => value.push(char),
-                        }
-                    }
-                    if error {
-                        exitcode = 65;
-                        let error = TokenError::new(
-                            "Error: Unterminated string.".to_string(),
-                            self.line,
-                            65,
-                        );
-                        writeln!(stderr(), "{}", error).unwrap()
-                    }
-                }
-                _ => {
-                    if char.is_alphabetic() || char == '_' {
-                        let mut buf = String::from(char);
-                        while let Some(&next_char) = characters.peek() {
-                            if next_char.is_ascii_alphanumeric() || next_char == '_' {
-                                buf.push(next_char);
-                                characters.next();
-                            } else {
-                                break;
-                            }
-                        }
-                        match buf.as_str() {
-                            "and" => tokens.push(Token::newToken(
-                                TokenType::And,
-                                "and".to_string(),
-                                None,
-                            )),
-                            "class" => tokens.push(Token::newToken(
-                                TokenType::Class,
-                                "class".to_string(),
-                                None,
-                            )),
-                            "else" => tokens.push(Token::newToken(
-                                TokenType::Else,
-                                "else".to_string(),
-                                None,
-                            )),
-                            "false" => tokens.push(Token::newToken(
-                                TokenType::False,
-                                "false".to_string(),
-                                None,
-                            )),
-                            "for" => tokens.push(Token::newToken(
-                                TokenType::For,
-                                "for".to_string(),
-                                None,
-                            )),
-                            "fun" => tokens.push(Token::newToken(
-                                TokenType::Fun,
-                                "fun".to_string(),
-                                None,
-                            )),
-                            "if" => {
-                                tokens.push(Token::newToken(TokenType::If, "if".to_string(), None))
-                            }
-                            "nil" => tokens.push(Token::newToken(
-                                TokenType::Nil,
-                                "nil".to_string(),
-                                None,
-                            )),
-                            "or" => {
-                                tokens.push(Token::newToken(TokenType::or, "or".to_string(), None))
-                            }
-                            "print" => tokens.push(Token::newToken(
-                                TokenType::Print,
-                                "print".to_string(),
-                                None,
-                            )),
-                            "return" => tokens.push(Token::newToken(
-                                TokenType::Return,
-                                "return".to_string(),
-                                None,
-                            )),
-                            "super" => tokens.push(Token::newToken(
-                                TokenType::Super,
-                                "super".to_string(),
-                                None,
-                            )),
-                            "this" => tokens.push(Token::newToken(
-                                TokenType::This,
-                                "this".to_string(),
-                                None,
-                            )),
-                            "true" => tokens.push(Token::newToken(
-                                TokenType::True,
-                                "true".to_string(),
-                                None,
-                            )),
-                            "var" => tokens.push(Token::newToken(
-                                TokenType::Var,
-                                "var".to_string(),
-                                None,
-                            )),
-                            "while" => tokens.push(Token::newToken(
-                                TokenType::While,
-                                "while".to_string(),
-                                None,
-                            )),
-                            _ => tokens.push(Token::newToken(TokenType::Identifer, buf, None)),
-                        }
-                    } else if char.is_ascii_digit() {
-                        let mut has_dot = false;
-                        let mut number = Vec::new();
-                        number.push(char);
-
-                        while let Some(&next_char) = characters.peek() {
-                            if next_char.is_ascii_digit() {
-                                number.push(next_char);
-                                characters.next();
-                            } else if next_char == '.' && !has_dot {
-                                number.push(next_char);
-                                has_dot = true;
-                                characters.next();
-                            } else {
-                                break;
-                            }
-                        }
-                        let mut numstr = number.clone().iter().collect::<String>();
-
-                        if numstr.ends_with('.') {
-                            numstr.push('0');
-                            let mut actual: String = number.iter().collect::<String>();
-                            actual.pop();
-
-                            tokens.push(Token::newToken(TokenType::Number, actual, Some(numstr)));
-                            tokens.push(Token::newToken(TokenType::Dot, ".".to_string(), None));
-                        } else if numstr.ends_with(".00") {
-                            numstr.pop();
-                            let actual = number.iter().collect::<String>();
-
-                            tokens.push(Token::newToken(TokenType::Number, actual, Some(numstr)));
-                        } else {
-                            if !numstr.contains('.') || !has_dot {
-                                numstr.push_str(".0");
-                            }
-                            tokens.push(Token::newToken(
-                                TokenType::Number,
-                                number.iter().collect::<String>(),
-                                Some(numstr),
-                            ));
-                        }
-                    } else {
-                        exitcode = 65;
-                        let error = TokenError::new(
-                            format!("Error: Unexpected character: {}", char),
-                            self.line,
-                            65,
-                        );
-                        if let Err(e) = writeln!(stderr(), "{error}") {
-                            eprintln!("Failed to write to stderr: {}", e);
-                        }
-                    }
-                }
-            };
-        }
-        self.tokens = tokens;
-        exitcode
+/// Writes a number the way the `tokenize` and `parse` output shows it: in
+/// full, never in exponent form, and with a fractional part even when it's
+/// whole, so `42` is `42.0` and `1e16` is `10000000000000000.0`.
+pub fn write_number(f: &mut fmt::Formatter<'_>, n: f64) -> fmt::Result {
+    if n.is_infinite() {
+        write_infinity(f, n)
+    } else if n.fract() == 0.0 {
+        write!(f, "{n}.0")
+    } else {
+        write!(f, "{n}")
     }
 }
-#[derive(Clone, Debug)]
-pub struct Token {
-    _type: TokenType,
-    _string: String,
-    _value: Option<String>,
+
+/// Writes an infinite number as Lox spells it, `Infinity` or `-Infinity`.
+pub fn write_infinity(f: &mut fmt::Formatter<'_>, n: f64) -> fmt::Result {
+    let sign = if n < 0.0 { "-" } else { "" };
+    write!(f, "{sign}Infinity")
 }
 
-impl fmt::Display for Token {
+impl fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let value = match &self._value {
-            Some(value) => value,
-            None => "null",
-        };
-        write!(f, "{} {} {}", self._type, self._string, value)
+        match &self._value {
+            Some(value) => write!(f, "{} {} {}", self._type, self._string, value),
+            None => write!(f, "{} {} null", self._type, self._string),
+        }
     }
 }
 
-impl Token {
-    pub fn newToken(_type: TokenType, _string: String, _value: Option<String>) -> Self {
+impl<'src> Token<'src> {
+    pub fn newToken(_type: TokenType, _string: &'src str, _value: Option<Literal<'src>>) -> Self {
         Self {
             _type,
             _string,
             _value,
+            _span: Span::default(),
         }
     }
 }
@@ -386,6 +108,9 @@ pub enum TokenType {
     GreaterThanEquals,
     Slash,
     String,
+    /// The part of an interpolated string before a `${`, including it. The
+    /// part after the last `}` is an ordinary `String` token.
+    Interpolation,
     Number,
     Identifer,
     And,
@@ -410,29 +135,6 @@ pub enum TokenType {
     NewLine,
 }
 
-#[derive(Debug)]
-struct TokenError {
-    msg: String,
-    line: i32,
-    exitcode: i32,
-}
-
-impl TokenError {
-    fn new(msg: String, line: i32, exitcode: i32) -> Self {
-        Self {
-            msg,
-            line,
-            exitcode,
-        }
-    }
-}
-
-impl std::fmt::Display for TokenError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[line {}] {}", self.line, self.msg)
-    }
-}
-
 impl std::fmt::Display for TokenType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -456,6 +158,7 @@ impl std::fmt::Display for TokenType {
             TokenType::LessThan => write!(f, "LESS"),
             TokenType::Slash => write!(f, "SLASH"),
             TokenType::String => write!(f, "STRING"),
+            TokenType::Interpolation => write!(f, "INTERPOLATION"),
             TokenType::Number => write!(f, "NUMBER"),
             TokenType::Identifer => write!(f, "IDENTIFIER"),
             TokenType::And => write!(f, "AND"),
@@ -479,13 +182,43 @@ impl std::fmt::Display for TokenType {
     }
 }
 
+// scans the whole file up front, stopping the run if anything in it didn't lex
+fn lex<'src>(source: &'src str, reporter: &Reporter) -> Vec<Token<'src>> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.by_ref().collect();
+    if !lexer.errors.is_empty() {
+        reporter.report(&lexer.errors);
+        exit(65);
+    }
+    tokens
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    // options can go anywhere on the command line; everything else is positional
+    let (options, args): (Vec<String>, Vec<String>) =
+        env::args().partition(|arg| arg.starts_with("--"));
     if args.len() < 3 {
-        writeln!(io::stderr(), "Usage: {} tokenize <filename>", args[0]).unwrap();
+        writeln!(
+            io::stderr(),
+            "Usage: {} tokenize <filename> [--diagnostics=plain|pretty]",
+            args[0]
+        )
+        .unwrap();
         return;
     }
 
+    let mut style = Style::Plain;
+    for option in &options {
+        match option.as_str() {
+            "--diagnostics=plain" => style = Style::Plain,
+            "--diagnostics=pretty" => style = Style::Pretty,
+            _ => {
+                writeln!(io::stderr(), "Unknown option: {}", option).unwrap();
+                exit(64);
+            }
+        }
+    }
+
     let command = &args[1];
     let filename = &args[2];
 
@@ -493,63 +226,51 @@ fn main() {
         writeln!(io::stderr(), "Failed to read file {}", filename).unwrap();
         String::new()
     });
+    // every phase reports through this so errors look the same wherever they come from
+    let reporter = Reporter {
+        style,
+        file: filename,
+        source: &file_contents,
+    };
 
     match command.as_str() {
         "tokenize" => {
             // You can use print statements as follows for debugging, they'll be visible when running tests.
             writeln!(io::stderr(), "Logs from your program will appear here!").unwrap();
 
-            let mut file_contents = file_contents.chars().peekable();
-            let mut lexer = Lexer::new();
-
-            let result = lexer.tokenize(&mut file_contents);
-            for token in lexer.tokens {
+            // tokens are printed as they're scanned, ending with EOF
+            let mut lexer = Lexer::new(&file_contents);
+            for token in lexer.by_ref() {
                 println!("{token}");
             }
-            println!("EOF  null");
-            exit(result)
+            reporter.report(&lexer.errors);
+            exit(if lexer.errors.is_empty() { 0 } else { 65 })
         }
         "parse" => {
-            let mut file_contents = file_contents.chars().peekable();
-            let mut lexer = Lexer::new();
-            let mut result = lexer.tokenize(&mut file_contents);
-            if result == 65 {
-                exit(65);
-            }
-            lexer
-                .tokens
-                .push(Token::newToken(TokenType::EOF, "".to_string(), None));
-
-            let tokens = lexer.tokens.clone();
-
-            let mut parser = parse::Parser::new(tokens.clone());
+            let mut result = 0;
+            let mut parser = parse::Parser::new(lex(&file_contents, &reporter));
 
             let expr = parser.parse();
             match expr {
                 Ok(tehe) => println!("{tehe}"),
                 Err(e) => {
-                    result = 65;
-                    writeln!(stderr(), "{e}");
+                    result = e.exit;
+                    reporter.report(&[e]);
                 }
             };
             exit(result);
         }
         "evaluate" => {
-            let mut file_contents = file_contents.chars().peekable();
-            let mut lexer = Lexer::new();
-            let mut result = lexer.tokenize(&mut file_contents);
-            if result == 65 {
-                exit(65);
-            }
-            lexer
-                .tokens
-                .push(Token::newToken(TokenType::EOF, "".to_string(), None));
-
-            let tokens = lexer.tokens.clone();
-
-            let mut parser = parse::Parser::new(tokens.clone());
+            let mut result = 0;
+            let mut parser = parse::Parser::new(lex(&file_contents, &reporter));
 
-            let expr = parser.parse().unwrap();
+            let expr = match parser.parse() {
+                Ok(expr) => expr,
+                Err(e) => {
+                    reporter.report(std::slice::from_ref(&e));
+                    exit(e.exit);
+                }
+            };
             let mut evaluator = Evaluator::new();
             let eval = evaluator.evaluate(&expr);
             match eval {
@@ -557,38 +278,44 @@ fn main() {
                     println!("{}", out)
                 }
                 Err(err) => {
+                    let err = Diagnostic::from(err);
                     result = err.exit;
-                    writeln!(stderr(), "{}", err).unwrap();
+                    reporter.report(&[err]);
                 }
             }
             exit(result);
         }
         "run" => {
-            let mut file_contents = file_contents.chars().peekable();
-            let mut lexer = Lexer::new();
-            let result = lexer.tokenize(&mut file_contents);
-            if result == 65 {
-                exit(65);
-            }
-            lexer
-                .tokens
-                .push(Token::newToken(TokenType::EOF, "".to_string(), None));
-            let tokens = lexer.tokens.clone();
-            let mut parser = parse::Parser::new(tokens);
+            let mut parser = parse::Parser::new(lex(&file_contents, &reporter));
             let statments = parser.stmt_parser();
             let statments = match statments {
                 Ok(s) => s,
-                Err(e) => {
-                    writeln!(stderr(), "{e}").unwrap();
-                    exit(e.code);
+                Err(errors) => {
+                    reporter.report(&errors);
+                    exit(65);
                 }
             };
+            let mut resolver = resolve::Resolver::new();
+            let resolved = resolver.resolve(&statments);
+            // warnings and errors are reported together, in source order
+            let mut diagnostics = std::mem::take(&mut resolver.warnings);
+            if let Err(errors) = &resolved {
+                diagnostics.extend_from_slice(errors);
+            }
+            diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+            reporter.report(&diagnostics);
+            let locals = match resolved {
+                Ok(locals) => locals,
+                Err(_) => exit(65),
+            };
             let mut interpreter = Interpreter::new();
+            interpreter.resolve(locals);
             let _error = interpreter.interpret(statments);
             match _error {
                 Ok(_a) => {}
                 Err(e) => {
-                    writeln!(stderr(), "{}", e.msg).unwrap();
+                    let e = Diagnostic::from(e);
+                    reporter.report(std::slice::from_ref(&e));
                     exit(e.exit)
                 }
             }
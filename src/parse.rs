@@ -1,133 +1,440 @@
 use anyhow::Error;
 
-use crate::expr::{Expr, ExprError, ExprLiteral};
-use crate::smnt::Statment;
+use crate::diagnostic::{Diagnostic, Phase};
+use crate::expr::{Expr, ExprKind, ExprLiteral};
+use std::rc::Rc;
+
+use crate::smnt::{ClassDecl, FunctionDecl, Statment, StatmentKind};
+use crate::Literal;
 use crate::Token;
 use crate::TokenType;
 
-pub struct Parser {
-    pub tokens: Vec<Token>,
-    pub expr: Vec<Expr>,
+/// How deep the syntax tree may get before parsing fails with an error.
+const MAX_NESTING: usize = 200;
+
+pub struct Parser<'src> {
+    pub tokens: Vec<Token<'src>>,
+    pub expr: Vec<Expr<'src>>,
     index: usize,
+    depth: usize,
+    /// Set once the nesting limit is hit and the rest of the input skipped.
+    too_deep: bool,
+    /// Syntax errors recovered from so far, in the order they were found.
+    errors: Vec<Diagnostic>,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+impl<'src> Parser<'src> {
+    pub fn new(mut tokens: Vec<Token<'src>>) -> Self {
+        // everything below relies on the stream ending in EOF, so never trust
+        // the caller to have added it
+        if tokens.last().map(|token| token._type) != Some(TokenType::EOF) {
+            let mut eof = Token::newToken(TokenType::EOF, "", None);
+            if let Some(last) = tokens.last() {
+                eof._span = last._span;
+                eof._span.start = last._span.end;
+            }
+            tokens.push(eof);
+        }
         Self {
             index: 0,
+            depth: 0,
+            too_deep: false,
             tokens,
             expr: Vec::new(),
+            errors: Vec::new(),
         }
     }
 
-    pub fn stmt_parser(&mut self) -> Result<Vec<Statment>, ExprError> {
+    /// Parses the whole program. A syntax error doesn't stop the parse: the
+    /// parser skips ahead to the next statement boundary and carries on, so
+    /// every error in the file is reported at once.
+    pub fn stmt_parser(&mut self) -> Result<Vec<Statment<'src>>, Vec<Diagnostic>> {
         let mut statments = Vec::new();
         while !self.is_at_end() {
-            statments.push(self.statement()?);
+            statments.extend(self.declaration());
+        }
+        if self.errors.is_empty() {
+            Ok(statments)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    // parses one statement, or records its syntax error and skips past it.
+    // Blocks recover through this too, so an error inside a body doesn't
+    // leave the rest of the body to be misread as top-level code.
+    fn declaration(&mut self) -> Option<Statment<'src>> {
+        match self.statement() {
+            Ok(statment) => Some(statment),
+            Err(e) => {
+                if !self.too_deep {
+                    self.errors.push(e);
+                }
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    // discards tokens until just after a `;` or just before a keyword that
+    // starts a statement, which is most likely where the next one begins
+    fn synchronize(&mut self) {
+        self.advance();
+        while !self.is_at_end() {
+            if self.prev()._type == TokenType::SemiColon {
+                return;
+            }
+            match self.peek()._type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => self.advance(),
+            }
+        }
+    }
+
+    // runs `parse` one level deeper and puts the depth back after, error or not
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, Diagnostic>,
+    ) -> Result<T, Diagnostic> {
+        let depth = self.depth;
+        self.deeper()?;
+        let result = parse(self);
+        self.depth = depth;
+        result
+    }
+
+    // one more level, taken by each statement, assignment, unary and chained node
+    fn deeper(&mut self) -> Result<(), Diagnostic> {
+        if self.depth >= MAX_NESTING {
+            let error = Diagnostic::at_token(Phase::Parse, self.peek(), "Too much nesting.");
+            self.errors.push(error.clone());
+            self.too_deep = true;
+            self.index = self.tokens.len() - 1;
+            return Err(error);
         }
-        Ok(statments)
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub fn statement(&mut self) -> Result<Statment<'src>, Diagnostic> {
+        self.nested(Self::statement_inner)
     }
 
-    pub fn statement(&mut self) -> Result<Statment, ExprError> {
-        if self.matchexpr(&[TokenType::Var]) {
+    fn statement_inner(&mut self) -> Result<Statment<'src>, Diagnostic> {
+        let start = self.peek()._span;
+        let kind = if self.matchexpr(&[TokenType::Var]) {
             //then it is a declaration :3
             self.var_decloration()
         } else if self.matchexpr(&[TokenType::Print]) {
             self.print_statment()
         } else if self.matchexpr(&[TokenType::LeftBrace]) {
             self.blockexpr()
+        } else if self.matchexpr(&[TokenType::If]) {
+            self.if_statment()
+        } else if self.matchexpr(&[TokenType::While]) {
+            self.while_statment()
+        } else if self.matchexpr(&[TokenType::For]) {
+            self.for_statment()
+        } else if self.matchexpr(&[TokenType::Class]) {
+            self.class_decloration()
+        } else if self.matchexpr(&[TokenType::Fun]) {
+            self.function("function")
+        } else if self.matchexpr(&[TokenType::Return]) {
+            self.return_statment()
         } else {
             self.expr_statment()
-        }
+        }?;
+        Ok(Statment::new(kind, start.to(self.prev()._span)))
     }
 
-    pub fn blockexpr(&mut self) -> Result<Statment, ExprError> {
+    pub fn blockexpr(&mut self) -> Result<StatmentKind<'src>, Diagnostic> {
         let mut statments = Vec::new();
-        while self.matchexpr(&[TokenType::RightBrace]) == false {
-            statments.push(self.statement()?)
+        while self.peek()._type != TokenType::RightBrace && !self.is_at_end() {
+            statments.extend(self.declaration());
         }
-        Ok(Statment::BlockStatment(Box::new(statments)))
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+        Ok(StatmentKind::BlockStatment(Box::new(statments)))
     }
 
-    pub fn var_decloration(&mut self) -> Result<Statment, ExprError> {
-        let identifier = self.equality()?;
-        //should actually check if it is of identifier type
-        if let Expr::Literal(ExprLiteral::Identifier(value)) = &identifier {
+    pub fn if_statment(&mut self) -> Result<StatmentKind<'src>, Diagnostic> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.parse()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = Box::new(self.statement()?);
+        let else_branch = if self.matchexpr(&[TokenType::Else]) {
+            Some(Box::new(self.statement()?))
         } else {
-            return Err(ExprError::new("expected an identifier".to_string(), 100));
-        }
+            None
+        };
+        Ok(StatmentKind::If(condition, then_branch, else_branch))
+    }
+
+    pub fn while_statment(&mut self) -> Result<StatmentKind<'src>, Diagnostic> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.parse()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+
+        let body = Box::new(self.statement()?);
+        Ok(StatmentKind::While(condition, body))
+    }
+
+    // there is no for node, `for (init; cond; incr) body` is desugared into
+    // `{ init; while (cond) { body; incr; } }`
+    pub fn for_statment(&mut self) -> Result<StatmentKind<'src>, Diagnostic> {
+        let start = self.prev()._span;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
 
-        //
-        if self.matchexpr(&[TokenType::SemiColon]) {
-            // then set it to nil
-            return Ok(Statment::VarDec(
-                identifier,
-                Expr::Literal(ExprLiteral::Nil),
-            ));
-        } else if self.matchexpr(&[TokenType::EQUAL]) {
-            // then set it to that expression
-            let expr = self.parse()?;
-            if self.matchexpr(&[TokenType::SemiColon]) {
-                // return ok
-                return Ok(Statment::VarDec(identifier, expr));
+        let initializer = if self.matchexpr(&[TokenType::SemiColon]) {
+            None
+        } else {
+            let init_start = self.peek()._span;
+            let kind = if self.matchexpr(&[TokenType::Var]) {
+                self.var_decloration()?
             } else {
-                return Err(ExprError::new("SemiColon expected".to_string(), 100));
-            }
+                self.expr_statment()?
+            };
+            Some(Statment::new(kind, init_start.to(self.prev()._span)))
+        };
+
+        let condition = if self.peek()._type == TokenType::SemiColon {
+            let span = self.peek()._span;
+            Expr::new(ExprKind::Literal(ExprLiteral::Bool(true)), span)
+        } else {
+            self.parse()?
+        };
+        self.consume(TokenType::SemiColon, "Expect ';' after loop condition.")?;
+
+        let increment = if self.peek()._type == TokenType::RightParen {
+            None
         } else {
-            return Err(ExprError::new("Unexpected token".to_string(), 100));
+            Some(self.parse()?)
+        };
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let mut body = self.statement()?;
+        if let Some(increment) = increment {
+            let span = body.span;
+            let increment_span = increment.span;
+            let increment = Statment::new(StatmentKind::ExprStmt(increment), increment_span);
+            body = Statment::new(
+                StatmentKind::BlockStatment(Box::new(vec![body, increment])),
+                span,
+            );
         }
+
+        let span = start.to(body.span);
+        let looped = Statment::new(StatmentKind::While(condition, Box::new(body)), span);
+        Ok(match initializer {
+            Some(initializer) => StatmentKind::BlockStatment(Box::new(vec![initializer, looped])),
+            None => looped.kind,
+        })
     }
 
-    pub fn expr_statment(&mut self) -> Result<Statment, ExprError> {
-        let expr = self.parse()?;
-        if self.matchexpr(&[TokenType::SemiColon]) {
-            Ok(Statment::ExprStmt(expr))
+    pub fn class_decloration(&mut self) -> Result<StatmentKind<'src>, Diagnostic> {
+        let name = self
+            .consume(TokenType::Identifer, "Expect class name.")?
+            .clone();
+
+        let superclass = if self.matchexpr(&[TokenType::LessThan]) {
+            let superclass = self.consume(TokenType::Identifer, "Expect superclass name.")?;
+            let literal = ExprLiteral::Identifier(superclass._string);
+            Some(Expr::new(ExprKind::Literal(literal), superclass._span))
         } else {
-            Err(ExprError::new("Semicolon expected".to_string(), 100))
+            None
+        };
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = Vec::new();
+        while self.peek()._type != TokenType::RightBrace && !self.is_at_end() {
+            match self.function("method")? {
+                StatmentKind::Function(method) => methods.push(method),
+                _ => unreachable!(),
+            }
         }
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+
+        Ok(StatmentKind::Class(Rc::new(ClassDecl {
+            name,
+            superclass,
+            methods,
+        })))
     }
 
-    pub fn print_statment(&mut self) -> Result<Statment, ExprError> {
-        let expr = self.parse()?;
-        if self.matchexpr(&[TokenType::SemiColon]) {
-            Ok(Statment::PrintStmt(expr))
-        } else {
-            Err(ExprError::new("Semicolon expected".to_string(), 100))
+    pub fn function(&mut self, kind: &str) -> Result<StatmentKind<'src>, Diagnostic> {
+        let name = self
+            .consume(TokenType::Identifer, &format!("Expect {kind} name."))?
+            .clone();
+        self.consume(
+            TokenType::LeftParen,
+            &format!("Expect '(' after {kind} name."),
+        )?;
+
+        let mut params = Vec::new();
+        if self.peek()._type != TokenType::RightParen {
+            loop {
+                if params.len() >= 255 {
+                    return Err(Diagnostic::at_token(
+                        Phase::Parse,
+                        self.peek(),
+                        "Can't have more than 255 parameters.",
+                    ));
+                }
+                let param = self.consume(TokenType::Identifer, "Expect parameter name.")?;
+                params.push(param.clone());
+                if !self.matchexpr(&[TokenType::Comma]) {
+                    break;
+                }
+            }
         }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {kind} body."),
+        )?;
+        let body = match self.blockexpr()? {
+            StatmentKind::BlockStatment(body) => *body,
+            _ => unreachable!(),
+        };
+        Ok(StatmentKind::Function(Rc::new(FunctionDecl {
+            name,
+            params,
+            body,
+        })))
+    }
+
+    pub fn return_statment(&mut self) -> Result<StatmentKind<'src>, Diagnostic> {
+        let keyword = self.prev().clone();
+        let value = if self.peek()._type == TokenType::SemiColon {
+            None
+        } else {
+            Some(self.parse()?)
+        };
+        self.consume(TokenType::SemiColon, "Expect ';' after return value.")?;
+        Ok(StatmentKind::Return(keyword, value))
+    }
+
+    pub fn var_decloration(&mut self) -> Result<StatmentKind<'src>, Diagnostic> {
+        let name = self
+            .consume(TokenType::Identifer, "Expect variable name.")?
+            .clone();
+
+        let value = if self.matchexpr(&[TokenType::EQUAL]) {
+            self.parse()?
+        } else {
+            // no initializer, so it starts out as nil
+            Expr::new(ExprKind::Literal(ExprLiteral::Nil), name._span)
+        };
+        self.consume(
+            TokenType::SemiColon,
+            "Expect ';' after variable declaration.",
+        )?;
+        Ok(StatmentKind::VarDec(name, value))
+    }
+
+    pub fn expr_statment(&mut self) -> Result<StatmentKind<'src>, Diagnostic> {
+        let expr = self.parse()?;
+        self.consume(TokenType::SemiColon, "Expect ';' after expression.")?;
+        Ok(StatmentKind::ExprStmt(expr))
+    }
+
+    pub fn print_statment(&mut self) -> Result<StatmentKind<'src>, Diagnostic> {
+        let expr = self.parse()?;
+        self.consume(TokenType::SemiColon, "Expect ';' after value.")?;
+        Ok(StatmentKind::PrintStmt(expr))
     }
 
-    pub fn parse(&mut self) -> Result<Expr, ExprError> {
+    pub fn parse(&mut self) -> Result<Expr<'src>, Diagnostic> {
         self.assignment()
     }
 
-    pub fn assignment(&mut self) -> Result<Expr, ExprError> {
-        let expr = self.equality()?;
+    pub fn assignment(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        self.nested(Self::assignment_inner)
+    }
+
+    fn assignment_inner(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let expr = self.logic_or()?;
 
         // Check if the next token is an EQUAL
         if self.matchexpr(&[TokenType::EQUAL]) {
+            let equals = self.prev().clone();
             // Ensure the left-hand side is a valid assignment target (e.g., Identifier)
-            if let Expr::Literal(ExprLiteral::Identifier(_)) = expr {
+            if let ExprKind::Literal(ExprLiteral::Identifier(_)) = expr.kind {
                 let value = self.assignment()?; // Evaluate the right-hand side
-                return Ok(Expr::Assignment(Box::new(expr), Box::new(value)));
+                let span = expr.span.to(value.span);
+                return Ok(Expr::new(
+                    ExprKind::Assignment(Box::new(expr), Box::new(value)),
+                    span,
+                ));
+            } else if let ExprKind::Get(object, name) = expr.kind {
+                // `a.b = c` parses `a.b` as a get first, turn it into a set
+                let value = self.assignment()?;
+                let span = expr.span.to(value.span);
+                return Ok(Expr::new(
+                    ExprKind::Set(object, name, Box::new(value)),
+                    span,
+                ));
             } else {
-                return Err(ExprError::new("Invalid assignment target".to_string(), 100));
+                return Err(Diagnostic::at_token(
+                    Phase::Parse,
+                    &equals,
+                    "Invalid assignment target.",
+                ));
             }
         }
 
         Ok(expr)
     }
 
-    pub fn equality(&mut self) -> Result<Expr, ExprError> {
+    pub fn logic_or(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let depth = self.depth;
+        let mut expr = self.logic_and()?;
+        while self.matchexpr(&[TokenType::or]) {
+            let operator = self.prev().clone();
+            self.deeper()?;
+            let right = self.logic_and()?;
+            expr = Self::logical(operator, expr, right);
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    pub fn logic_and(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let depth = self.depth;
+        let mut expr = self.equality()?;
+        while self.matchexpr(&[TokenType::And]) {
+            let operator = self.prev().clone();
+            self.deeper()?;
+            let right = self.equality()?;
+            expr = Self::logical(operator, expr, right);
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    pub fn equality(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let depth = self.depth;
         let mut expr = self.comparison()?;
         while self.matchexpr(&[TokenType::BangEqual, TokenType::EqualEqual]) {
             let operator = self.prev().clone();
+            self.deeper()?;
             let right = self.comparison()?;
-            expr = Expr::Binary(operator, Box::new(expr), Box::new(right));
+            expr = Self::binary(operator, expr, right);
         }
+        self.depth = depth;
         Ok(expr)
     }
 
-    pub fn comparison(&mut self) -> Result<Expr, ExprError> {
+    pub fn comparison(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let depth = self.depth;
         let mut expr = self.term()?;
         while self.matchexpr(&[
             TokenType::GreaterThan,
@@ -136,100 +443,216 @@ impl Parser {
             TokenType::LessThanEquals,
         ]) {
             let operator = self.prev().clone();
+            self.deeper()?;
             let right = self.term()?;
-            expr = Expr::Binary(operator, Box::new(expr), Box::new(right));
+            expr = Self::binary(operator, expr, right);
         }
+        self.depth = depth;
         Ok(expr)
     }
 
-    pub fn term(&mut self) -> Result<Expr, ExprError> {
+    pub fn term(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let depth = self.depth;
         let mut expr = self.factor()?;
         while self.matchexpr(&[TokenType::Minus, TokenType::Plus]) {
             let operator = self.prev().clone();
+            self.deeper()?;
             let right = self.factor()?;
-            expr = Expr::Binary(operator, Box::new(expr), Box::new(right));
+            expr = Self::binary(operator, expr, right);
         }
+        self.depth = depth;
         Ok(expr)
     }
 
-    pub fn factor(&mut self) -> Result<Expr, ExprError> {
+    pub fn factor(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let depth = self.depth;
         let mut expr = self.unary()?;
         while self.matchexpr(&[TokenType::Slash, TokenType::Star]) {
             let operator = self.prev().clone();
+            self.deeper()?;
             let right = self.unary()?;
-            expr = Expr::Binary(operator, Box::new(expr), Box::new(right));
+            expr = Self::binary(operator, expr, right);
         }
+        self.depth = depth;
         Ok(expr)
     }
 
-    pub fn unary(&mut self) -> Result<Expr, ExprError> {
+    pub fn unary(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        self.nested(Self::unary_inner)
+    }
+
+    fn unary_inner(&mut self) -> Result<Expr<'src>, Diagnostic> {
         if self.matchexpr(&[TokenType::Bang, TokenType::Minus]) {
             let operator = self.prev().clone();
             let expr = self.unary()?;
-            return Ok(Expr::Unary(operator, Box::new(expr)));
+            let span = operator._span.to(expr.span);
+            return Ok(Expr::new(ExprKind::Unary(operator, Box::new(expr)), span));
         }
 
-        self.primary()
+        self.call()
     }
 
-    pub fn primary(&mut self) -> Result<Expr, ExprError> {
-        let current = self
-            .tokens
-            .get(self.index)
-            .ok_or_else(|| ExprError::new("Unexpected end of input".to_string(), 65))?;
-        match current._type {
-            TokenType::Identifer => {
-                let identifer_string = current._string.clone();
-
-                self.advance();
-                Ok(Expr::Literal(ExprLiteral::Identifier(identifer_string)))
+    pub fn call(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let depth = self.depth;
+        let mut expr = self.primary()?;
+        loop {
+            if self.matchexpr(&[TokenType::LeftParen]) {
+                self.deeper()?;
+                expr = self.finish_call(expr)?;
+            } else if self.matchexpr(&[TokenType::Dot]) {
+                self.deeper()?;
+                let name = self
+                    .consume(TokenType::Identifer, "Expect property name after '.'.")?
+                    .clone();
+                let span = expr.span.to(name._span);
+                expr = Expr::new(ExprKind::Get(Box::new(expr), name), span);
+            } else {
+                break;
             }
-            TokenType::True => {
-                self.advance();
-                Ok(Expr::Literal(ExprLiteral::Bool(true)))
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr<'src>) -> Result<Expr<'src>, Diagnostic> {
+        let mut arguments = Vec::new();
+        if self.peek()._type != TokenType::RightParen {
+            loop {
+                if arguments.len() >= 255 {
+                    return Err(Diagnostic::at_token(
+                        Phase::Parse,
+                        self.peek(),
+                        "Can't have more than 255 arguments.",
+                    ));
+                }
+                arguments.push(self.parse()?);
+                if !self.matchexpr(&[TokenType::Comma]) {
+                    break;
+                }
             }
-            TokenType::False => {
+        }
+        let paren = self
+            .consume(TokenType::RightParen, "Expect ')' after arguments.")?
+            .clone();
+        let span = callee.span.to(paren._span);
+        Ok(Expr::new(
+            ExprKind::Call(Box::new(callee), paren, arguments),
+            span,
+        ))
+    }
+
+    pub fn primary(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let current = self.peek();
+        let span = current._span;
+        let missing =
+            |what| Diagnostic::at_token(Phase::Parse, current, &format!("Expect {what} value."));
+        let literal = match current._type {
+            TokenType::Identifer => ExprLiteral::Identifier(current._string),
+            TokenType::True => ExprLiteral::Bool(true),
+            TokenType::False => ExprLiteral::Bool(false),
+            TokenType::Nil => ExprLiteral::Nil,
+            TokenType::Super => {
+                let keyword = current.clone();
                 self.advance();
-                Ok(Expr::Literal(ExprLiteral::Bool(false)))
+                self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+                let method = self
+                    .consume(TokenType::Identifer, "Expect superclass method name.")?
+                    .clone();
+                let span = span.to(method._span);
+                return Ok(Expr::new(ExprKind::Super(keyword, method), span));
             }
-            TokenType::Nil => {
+            TokenType::This => {
+                let keyword = current.clone();
                 self.advance();
-                Ok(Expr::Literal(ExprLiteral::Nil))
+                return Ok(Expr::new(ExprKind::This(keyword), span));
             }
-            TokenType::String => {
-                let value = current
-                    ._value
-                    .clone()
-                    .ok_or_else(|| ExprError::new("Expected string value".to_string(), 65))?;
+            TokenType::String => match &current._value {
+                Some(Literal::String(value)) => ExprLiteral::String(value.clone()),
+                _ => return Err(missing("string")),
+            },
+            TokenType::Number => match current._value {
+                Some(Literal::Number(value)) => ExprLiteral::Number(value),
+                _ => return Err(missing("number")),
+            },
+            TokenType::Interpolation => return self.interpolation(),
+            TokenType::LeftParen => {
                 self.advance();
-                Ok(Expr::Literal(ExprLiteral::String(value)))
+                let inner = self.parse()?;
+                let close = self
+                    .consume(TokenType::RightParen, "Expect ')' after expression.")?
+                    ._span;
+                let span = span.to(close);
+                return Ok(Expr::new(ExprKind::Grouping(Box::new(inner)), span));
             }
-            TokenType::Number => {
-                let value = current
-                    ._value
-                    .clone()
-                    .ok_or_else(|| ExprError::new("Expected number value".to_string(), 65))?;
-                self.advance();
-                Ok(Expr::Literal(ExprLiteral::Number(value)))
+            _ => {
+                return Err(Diagnostic::at_token(
+                    Phase::Parse,
+                    current,
+                    "Expect expression.",
+                ))
             }
-            TokenType::LeftParen => {
-                self.advance();
-                let inner = self.equality()?;
-                if let Some(tok) = self.tokens.get(self.index) {
-                    if tok._type == TokenType::RightParen {
-                        self.advance();
-                        Ok(Expr::Grouping(Box::new(inner)))
-                    } else {
-                        Err(ExprError::new("Expected ')'".to_string(), 65))
-                    }
-                } else {
-                    Err(ExprError::new("Expected ')'".to_string(), 65))
+        };
+        self.advance();
+        Ok(Expr::new(ExprKind::Literal(literal), span))
+    }
+
+    // an `Interpolation` token, then an expression after each one, until the
+    // `String` token holding the text after the last `}`
+    fn interpolation(&mut self) -> Result<Expr<'src>, Diagnostic> {
+        let start = self.peek()._span;
+        let mut parts = Vec::new();
+        loop {
+            let segment = self.peek().clone();
+            self.advance();
+            if let Some(Literal::String(text)) = &segment._value {
+                if !text.is_empty() {
+                    let literal = ExprLiteral::String(text.clone());
+                    parts.push(Expr::new(ExprKind::Literal(literal), segment._span));
                 }
             }
-            _ => Err(ExprError::new("Unexpected token".to_string(), 65)),
+            if segment._type == TokenType::String {
+                let span = start.to(segment._span);
+                return Ok(Expr::new(ExprKind::Interpolation(parts), span));
+            }
+            // `${}` has nothing to parse: the next token is already the segment
+            // starting at its `}`, which `parse` would take for a string literal
+            if self.peek()._string.starts_with('}') {
+                return Err(Diagnostic::at_token(
+                    Phase::Parse,
+                    self.peek(),
+                    "Expect expression.",
+                ));
+            }
+            parts.push(self.parse()?);
+            if !matches!(
+                self.peek()._type,
+                TokenType::Interpolation | TokenType::String
+            ) {
+                return Err(Diagnostic::at_token(
+                    Phase::Parse,
+                    self.peek(),
+                    "Expect '}' after interpolated expression.",
+                ));
+            }
         }
     }
 
+    fn binary(operator: Token<'src>, left: Expr<'src>, right: Expr<'src>) -> Expr<'src> {
+        let span = left.span.to(right.span);
+        Expr::new(
+            ExprKind::Binary(operator, Box::new(left), Box::new(right)),
+            span,
+        )
+    }
+
+    fn logical(operator: Token<'src>, left: Expr<'src>, right: Expr<'src>) -> Expr<'src> {
+        let span = left.span.to(right.span);
+        Expr::new(
+            ExprKind::Logical(operator, Box::new(left), Box::new(right)),
+            span,
+        )
+    }
+
     pub fn matchexpr(&mut self, types: &[TokenType]) -> bool {
         for _type in types {
             if self.peek()._type == *_type {
@@ -240,6 +663,14 @@ impl Parser {
         false
     }
 
+    pub fn consume(&mut self, _type: TokenType, msg: &str) -> Result<&Token<'src>, Diagnostic> {
+        if self.matchexpr(&[_type]) {
+            Ok(self.prev())
+        } else {
+            Err(Diagnostic::at_token(Phase::Parse, self.peek(), msg))
+        }
+    }
+
     pub fn advance(&mut self) {
         if !self.is_at_end() {
             self.index += 1;
@@ -250,15 +681,103 @@ impl Parser {
         matches!(self.peek()._type, TokenType::EOF)
     }
 
-    pub fn peek(&self) -> &Token {
-        self.tokens
-            .get(self.index)
-            .expect("Unexpected end of input")
+    // `advance` never steps past the trailing EOF, so both of these stay in
+    // bounds; running out of input just keeps yielding EOF
+    pub fn peek(&self) -> &Token<'src> {
+        &self.tokens[self.index.min(self.tokens.len() - 1)]
     }
 
-    pub fn prev(&self) -> &Token {
-        self.tokens
-            .get(self.index.saturating_sub(1))
-            .expect("No previous token")
+    pub fn prev(&self) -> &Token<'src> {
+        &self.tokens[self.index.saturating_sub(1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::Lexer;
+
+    fn errors(source: &str) -> Vec<String> {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.by_ref().collect();
+        assert!(lexer.errors.is_empty());
+        match Parser::new(tokens).stmt_parser() {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn errors_inside_a_body_recover_within_the_body() {
+        assert_eq!(
+            errors("fun f() {\n var x = ;\n print 1;\n}\nprint 2;"),
+            ["[line 2] Error at ';': Expect expression."]
+        );
+        assert_eq!(
+            errors("class A {\n m() {\n  print ;\n  var = 1;\n }\n}"),
+            [
+                "[line 3] Error at ';': Expect expression.",
+                "[line 4] Error at '=': Expect variable name.",
+            ]
+        );
+    }
+    #[test]
+    fn empty_interpolations_are_an_error() {
+        assert_eq!(
+            errors("print \"${}\" \"x\";"),
+            ["[line 1] Error at '}\"': Expect expression."]
+        );
+        assert_eq!(
+            errors("print \"a${}b\";"),
+            ["[line 1] Error at '}b\"': Expect expression."]
+        );
+    }
+
+    // on a thread with the main thread's stack, since test threads get less
+    #[test]
+    fn deep_nesting_is_an_error_not_a_crash() {
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(deep_nesting)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    fn deep_nesting() {
+        let expected = ["[line 1] Error at '(': Too much nesting."];
+        let parens = format!("print {}1{};", "(".repeat(20_000), ")".repeat(20_000));
+        assert_eq!(errors(&parens), expected);
+
+        let blocks = format!("{}{}", "{".repeat(20_000), "}".repeat(20_000));
+        assert_eq!(
+            errors(&blocks),
+            ["[line 1] Error at '{': Too much nesting."]
+        );
+
+        let shallow = format!("print {}1{};", "(".repeat(50), ")".repeat(50));
+        assert!(errors(&shallow).is_empty());
+    }
+
+    // flat in the source, but each link nests the tree one level deeper
+    #[test]
+    fn long_chains_count_toward_the_nesting_limit() {
+        let cases = [
+            ("1", " + 1", "1"),
+            ("1", " * 1", "1"),
+            ("true", " or true", "true"),
+            ("a", " == a", "a"),
+            ("f", "()", ")"),
+            ("a", ".b", "b"),
+        ];
+
+        for (first, link, at) in cases {
+            let long = format!("print {first}{};", link.repeat(50_000));
+            let expected = format!("[line 1] Error at '{at}': Too much nesting.");
+            assert_eq!(errors(&long), [expected], "{link}");
+
+            let short = format!("print {first}{};", link.repeat(100));
+            assert!(errors(&short).is_empty(), "{link}");
+        }
     }
 }
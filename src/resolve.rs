@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+
+use crate::diagnostic::{Diagnostic, Phase};
+use crate::environment::Binding;
+use crate::expr::{Expr, ExprKind, ExprLiteral};
+use crate::smnt::{ClassDecl, FunctionDecl, Statment, StatmentKind};
+use crate::span::Span;
+use crate::Token;
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+// a local as the resolver tracks it while its scope is open
+struct Local {
+    slot: usize,
+    initialized: bool,
+    declared: Span,
+    // set for a `var` until something refers to it
+    unused: bool,
+}
+
+/// Static pass run between parsing and interpreting. It works out which
+/// scope and slot every local variable lives in, and reports the mistakes
+/// that can be caught without running anything.
+pub struct Resolver {
+    // innermost scope last
+    scopes: Vec<HashMap<String, Local>>,
+    locals: HashMap<usize, Binding>,
+    errors: Vec<Diagnostic>,
+    /// Problems that don't stop the program from running, such as unused locals.
+    pub warnings: Vec<Diagnostic>,
+    function: FunctionType,
+    class: ClassType,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            locals: HashMap::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            function: FunctionType::None,
+            class: ClassType::None,
+        }
+    }
+
+    /// Returns where each local reference and declaration lives, keyed by
+    /// the byte offset of its name, in the shape `Evaluator::locals` expects.
+    /// A declaration's binding always has a depth of 0.
+    pub fn resolve(
+        &mut self,
+        statments: &[Statment],
+    ) -> Result<HashMap<usize, Binding>, Vec<Diagnostic>> {
+        self.resolve_all(statments);
+        if self.errors.is_empty() {
+            Ok(std::mem::take(&mut self.locals))
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    fn resolve_all(&mut self, statments: &[Statment]) {
+        for statment in statments {
+            self.resolve_statment(statment);
+        }
+    }
+
+    fn resolve_statment(&mut self, statment: &Statment) {
+        match &statment.kind {
+            StatmentKind::ExprStmt(expr) | StatmentKind::PrintStmt(expr) => self.resolve_expr(expr),
+            StatmentKind::VarDec(name, value) => {
+                if self.declare(name) {
+                    if let Some(local) = self.local_mut(name._string) {
+                        local.unused = true;
+                    }
+                }
+                self.resolve_expr(value);
+                self.define(name);
+            }
+            StatmentKind::BlockStatment(statments) => {
+                self.begin_scope();
+                self.resolve_all(statments);
+                self.end_scope();
+            }
+            StatmentKind::If(condition, then_branch, else_branch) => {
+                self.resolve_expr(condition);
+                self.resolve_statment(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_statment(else_branch);
+                }
+            }
+            StatmentKind::While(condition, body) => {
+                self.resolve_expr(condition);
+                self.resolve_statment(body);
+            }
+            StatmentKind::Function(declaration) => {
+                // defined up front so the body can call itself
+                self.declare(&declaration.name);
+                self.define(&declaration.name);
+                self.resolve_function(declaration, FunctionType::Function);
+            }
+            StatmentKind::Return(keyword, value) => {
+                if self.function == FunctionType::None {
+                    self.error(keyword, "Can't return from top-level code.");
+                }
+                if let Some(value) = value {
+                    if self.function == FunctionType::Initializer {
+                        self.error(keyword, "Can't return a value from an initializer.");
+                    }
+                    self.resolve_expr(value);
+                }
+            }
+            StatmentKind::Class(declaration) => self.resolve_class(declaration),
+        }
+    }
+
+    fn resolve_class(&mut self, declaration: &ClassDecl) {
+        let enclosing = self.class;
+        self.class = ClassType::Class;
+
+        self.declare(&declaration.name);
+        self.define(&declaration.name);
+
+        if let Some(superclass) = &declaration.superclass {
+            if let ExprKind::Literal(ExprLiteral::Identifier(name)) = &superclass.kind {
+                if *name == declaration.name._string {
+                    self.error_at(name, superclass.span, "A class can't inherit from itself.");
+                }
+            }
+            self.class = ClassType::Subclass;
+            self.resolve_expr(superclass);
+
+            self.begin_scope();
+            self.put("super");
+        }
+
+        self.begin_scope();
+        self.put("this");
+        for method in &declaration.methods {
+            let kind = if method.name._string == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, kind);
+        }
+        self.end_scope();
+
+        if declaration.superclass.is_some() {
+            self.end_scope();
+        }
+        self.class = enclosing;
+    }
+
+    fn resolve_function(&mut self, declaration: &FunctionDecl, kind: FunctionType) {
+        let enclosing = self.function;
+        self.function = kind;
+
+        self.begin_scope();
+        for param in &declaration.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_all(&declaration.body);
+        self.end_scope();
+
+        self.function = enclosing;
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(ExprLiteral::Identifier(name)) => {
+                let declared = self.scopes.last().and_then(|scope| scope.get(*name));
+                if declared.is_some_and(|local| !local.initialized) {
+                    self.error_at(
+                        name,
+                        expr.span,
+                        "Can't read local variable in its own initializer.",
+                    );
+                }
+                self.resolve_local(name, expr.span);
+            }
+            ExprKind::Literal(_) => {}
+            ExprKind::Grouping(inner) => self.resolve_expr(inner),
+            ExprKind::Unary(_, right) => self.resolve_expr(right),
+            ExprKind::Binary(_, left, right) | ExprKind::Logical(_, left, right) => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            ExprKind::Assignment(target, value) => {
+                self.resolve_expr(value);
+                if let ExprKind::Literal(ExprLiteral::Identifier(name)) = &target.kind {
+                    self.resolve_local(name, target.span);
+                }
+            }
+            ExprKind::Call(callee, _, arguments) => {
+                self.resolve_expr(callee);
+                for argument in arguments {
+                    self.resolve_expr(argument);
+                }
+            }
+            ExprKind::Interpolation(parts) => {
+                for part in parts {
+                    self.resolve_expr(part);
+                }
+            }
+            ExprKind::Get(object, _) => self.resolve_expr(object),
+            ExprKind::Set(object, _, value) => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            }
+            ExprKind::This(keyword) => {
+                if self.class == ClassType::None {
+                    self.error(keyword, "Can't use 'this' outside of a class.");
+                    return;
+                }
+                self.resolve_local("this", keyword._span);
+            }
+            ExprKind::Super(keyword, _) => {
+                match self.class {
+                    ClassType::None => self.error(keyword, "Can't use 'super' outside of a class."),
+                    ClassType::Class => {
+                        self.error(keyword, "Can't use 'super' in a class with no superclass.")
+                    }
+                    ClassType::Subclass => {}
+                }
+                self.resolve_local("super", keyword._span);
+            }
+        }
+    }
+
+    // globals are left out of `locals` entirely and looked up by name
+    fn resolve_local(&mut self, name: &str, span: Span) {
+        for (depth, scope) in self.scopes.iter_mut().rev().enumerate() {
+            if let Some(local) = scope.get_mut(name) {
+                local.unused = false;
+                let slot = local.slot;
+                self.locals.insert(span.start, Binding { depth, slot });
+                return;
+            }
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        let mut unused: Vec<_> = scope
+            .into_iter()
+            .filter(|(_, local)| local.unused)
+            .collect();
+        unused.sort_by_key(|(_, local)| local.declared.start);
+        for (name, local) in unused {
+            let msg = format!("Local variable '{name}' is never used.");
+            self.warnings
+                .push(Diagnostic::warning(Phase::Resolve, local.declared, msg));
+        }
+    }
+
+    fn local_mut(&mut self, name: &str) -> Option<&mut Local> {
+        self.scopes.last_mut()?.get_mut(name)
+    }
+
+    // returns whether a new local was added, which it isn't for a global or
+    // for a name the scope already has
+    fn declare(&mut self, name: &Token) -> bool {
+        let Some(scope) = self.scopes.last_mut() else {
+            return false;
+        };
+        if let Some(previous) = scope.get(name._string) {
+            let note = format!(
+                "'{}' is first declared on line {}.",
+                name._string, previous.declared.line
+            );
+            let error = Diagnostic::at_token(
+                Phase::Resolve,
+                name,
+                "Already a variable with this name in this scope.",
+            );
+            self.errors.push(error.with_note(note));
+            return false;
+        }
+        // slots are handed out in declaration order, the same order the
+        // interpreter runs the declarations in
+        let slot = scope.len();
+        let local = Local {
+            slot,
+            initialized: false,
+            declared: name._span,
+            unused: false,
+        };
+        scope.insert(name._string.to_string(), local);
+        self.locals
+            .insert(name._span.start, Binding { depth: 0, slot });
+        true
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(local) = self.local_mut(name._string) {
+            local.initialized = true;
+        }
+    }
+
+    // binds one of the implicit names (`this`, `super`) in the innermost
+    // scope, which is always a fresh one holding nothing else, so slot 0
+    fn put(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            let local = Local {
+                slot: 0,
+                initialized: true,
+                declared: Span::default(),
+                unused: false,
+            };
+            scope.insert(name.to_string(), local);
+        }
+    }
+
+    fn error(&mut self, token: &Token, msg: &str) {
+        self.errors
+            .push(Diagnostic::at_token(Phase::Resolve, token, msg));
+    }
+
+    fn error_at(&mut self, lexeme: &str, span: Span, msg: &str) {
+        self.errors
+            .push(Diagnostic::at(Phase::Resolve, lexeme, span, msg));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Severity;
+    use crate::lex::Lexer;
+    use crate::parse::Parser;
+
+    // what the resolver recorded for the name at the last place `at` appears
+    fn binding(source: &str, at: &str) -> Option<Binding> {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.by_ref().collect();
+        let statments = Parser::new(tokens).stmt_parser().unwrap();
+        let locals = Resolver::new().resolve(&statments).unwrap();
+        locals.get(&source.rfind(at).unwrap()).copied()
+    }
+
+    #[test]
+    fn locals_resolve_to_a_depth_and_slot() {
+        let source = "var g; { var a; var b; { var c; print b + c + g; } }";
+        assert_eq!(binding(source, "b + "), Some(Binding { depth: 1, slot: 1 }));
+        assert_eq!(binding(source, "c + "), Some(Binding { depth: 0, slot: 0 }));
+        assert_eq!(binding(source, "g; }"), None);
+
+        let source = "fun f(x, y) { var z; return y + z; }";
+        assert_eq!(binding(source, "y + "), Some(Binding { depth: 0, slot: 1 }));
+        assert_eq!(binding(source, "z; }"), Some(Binding { depth: 0, slot: 2 }));
+    }
+    #[test]
+    fn unused_locals_warn_and_redeclarations_note_the_first() {
+        let source = "{ var a = 1; var b = 2; print b; var a = 3; }";
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.by_ref().collect();
+        let statments = Parser::new(tokens).stmt_parser().unwrap();
+        let mut resolver = Resolver::new();
+        let errors = resolver.resolve(&statments).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].notes, ["'a' is first declared on line 1."]);
+        let warnings: Vec<_> = resolver.warnings.iter().map(|w| &w.message).collect();
+        assert_eq!(warnings, ["Local variable 'a' is never used."]);
+        assert_eq!(resolver.warnings[0].severity, Severity::Warning);
+
+        // the rejected redeclaration doesn't stand in for the local it clashes with
+        let source = "{ var x = 1; print x; var x = 2; }";
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.by_ref().collect();
+        let statments = Parser::new(tokens).stmt_parser().unwrap();
+        let mut resolver = Resolver::new();
+        assert_eq!(resolver.resolve(&statments).unwrap_err().len(), 1);
+        assert!(resolver.warnings.is_empty());
+    }
+}
@@ -1,11 +1,48 @@
+use std::rc::Rc;
+
 use crate::expr::Expr;
+use crate::span::Span;
+use crate::Token;
 
 struct StatementError {}
 
 #[derive(Debug, Clone)]
-pub enum Statment {
-    ExprStmt(Expr),
-    PrintStmt(Expr),
-    BlockStatment(Box<Vec<Statment>>),
-    VarDec(Expr, Expr),
+pub struct Statment<'src> {
+    pub kind: StatmentKind<'src>,
+    pub span: Span,
+}
+
+impl<'src> Statment<'src> {
+    pub fn new(kind: StatmentKind<'src>, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum StatmentKind<'src> {
+    ExprStmt(Expr<'src>),
+    PrintStmt(Expr<'src>),
+    BlockStatment(Box<Vec<Statment<'src>>>),
+    VarDec(Token<'src>, Expr<'src>),
+    If(Expr<'src>, Box<Statment<'src>>, Option<Box<Statment<'src>>>),
+    While(Expr<'src>, Box<Statment<'src>>),
+    Function(Rc<FunctionDecl<'src>>),
+    Return(Token<'src>, Option<Expr<'src>>),
+    Class(Rc<ClassDecl<'src>>),
+}
+
+/// A `fun` declaration. Shared behind an `Rc` so every function value made
+/// from it points at the same body instead of copying it.
+#[derive(Debug)]
+pub struct FunctionDecl<'src> {
+    pub name: Token<'src>,
+    pub params: Vec<Token<'src>>,
+    pub body: Vec<Statment<'src>>,
+}
+
+#[derive(Debug)]
+pub struct ClassDecl<'src> {
+    pub name: Token<'src>,
+    pub superclass: Option<Expr<'src>>,
+    pub methods: Vec<Rc<FunctionDecl<'src>>>,
 }
@@ -0,0 +1,34 @@
+/// Location of a piece of source text: the 1-based line and column it starts
+/// at, plus the byte range `start..end` into the source string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub line: u32,
+    pub column: u32,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(line: u32, column: u32, start: usize, end: usize) -> Self {
+        Self {
+            line,
+            column,
+            start,
+            end,
+        }
+    }
+
+    /// Smallest span covering both `self` and `other`, keeping the line and
+    /// column of whichever starts first.
+    pub fn to(self, other: Span) -> Span {
+        let first = if self.start <= other.start {
+            self
+        } else {
+            other
+        };
+        Span {
+            end: self.end.max(other.end),
+            ..first
+        }
+    }
+}